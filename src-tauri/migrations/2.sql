@@ -0,0 +1 @@
+ALTER TABLE profile_table ADD COLUMN browser_path TEXT;
@@ -0,0 +1,3 @@
+ALTER TABLE profile_table ADD COLUMN restart_policy TEXT NOT NULL DEFAULT 'never';
+ALTER TABLE profile_table ADD COLUMN restart_max_retries INTEGER NOT NULL DEFAULT 3;
+ALTER TABLE profile_table ADD COLUMN restart_backoff_ms INTEGER NOT NULL DEFAULT 1000;
@@ -0,0 +1,2 @@
+ALTER TABLE profile_table ADD COLUMN extra_args TEXT;
+ALTER TABLE profile_table ADD COLUMN extra_env TEXT;
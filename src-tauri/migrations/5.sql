@@ -0,0 +1 @@
+ALTER TABLE profile_table ADD COLUMN launch_mode TEXT NOT NULL DEFAULT 'headed';
@@ -0,0 +1,639 @@
+use crate::core::error::ShadowError;
+use crate::core::instances::{list_chrome_instances, wait_for_profile};
+use crate::core::profile::{
+    close_profile, open_profile, ChromeOpenInfo, CloseInfo, ClosedProfileInfo, LaunchOverrides,
+    OpenInfo,
+};
+use crate::core::proxy_manager::ProxyStatus;
+use crate::core::shutdown::{shutdown_signal, ShutdownOptions};
+use crate::core::store::{
+    check_proxy, create_group, create_profile, delete_group, delete_profile, import_proxies,
+    query_groups, query_profiles, query_proxies, CommonInfo, DeletedInfo, GroupInfo, IdInfo,
+    ImportInfo, ImportResult, NewCommonInfo, NewProfileInfo, PageInfo, PageResult, ProfileInfo,
+    ProxyCheckInfo,
+};
+use crate::core::{open_app_data, API_ADDR};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::Read;
+use std::str::FromStr;
+use std::time::Duration;
+use std::{fs, io};
+use tokio::runtime::Runtime;
+use tokio::time::sleep;
+
+const USAGE: &str = "\
+Usage: shadow [--json] <command>
+
+Commands:
+  profile list [--group <id>] [--page <n>] [--page-size <n>]
+  profile create <name> [--group <id>] [--proxy <id>] [--remark <text>]
+  profile delete <id>
+  profile open <id> [--headless] [--port <port>]
+  profile close <id>
+  group list [--page <n>] [--page-size <n>]
+  group create <name> [--remark <text>]
+  group delete <id>
+  proxy list [--page <n>] [--page-size <n>]
+  proxy check <id|url>
+  proxy import <file|->
+  instances
+
+Goes through the running app when there is one and works on its data
+directly otherwise. `--group -1` lists ungrouped profiles; `--json` prints
+what the API returns instead of a table.
+";
+
+const COMMANDS: &[&str] = &["profile", "group", "proxy", "instances", "help"];
+const FLAGS: &[&str] = &["--json", "--headless", "--help"];
+const OPTIONS: &[&str] = &[
+    "--group",
+    "--page",
+    "--page-size",
+    "--port",
+    "--proxy",
+    "--remark",
+];
+const DEFAULT_PAGE_SIZE: u16 = 100;
+
+/// Whether the command line asks for the CLI rather than the app.
+pub fn is_cli_command(args: &[String]) -> bool {
+    let command = args.iter().find(|arg| !arg.starts_with('-'));
+    command.is_some_and(|command| COMMANDS.contains(&command.as_str()))
+        || args.iter().any(|arg| arg == "--help" || arg == "-h")
+}
+
+/// Runs one CLI command and returns the process exit code.
+pub fn run_cli(args: Vec<String>) -> i32 {
+    #[cfg(windows)]
+    attach_console();
+    let (json, result) = match Args::parse(args) {
+        Ok(args) if args.flag("--help") || args.words.first().is_some_and(|w| w == "help") => {
+            print!("{}", USAGE);
+            return 0;
+        }
+        Ok(args) => {
+            let runtime = Runtime::new().expect("failed to start tokio runtime");
+            let result = runtime.block_on(run(&args));
+            // Relays and process watchers started while working locally must
+            // not hold up the exit.
+            runtime.shutdown_background();
+            (args.flag("--json"), result)
+        }
+        Err(e) => (false, Err(e)),
+    };
+    let Err(e) = result else {
+        return 0;
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&e).unwrap_or_default());
+    } else {
+        eprintln!("Error: {}", e.message);
+    }
+    if e.code == USAGE_ERROR {
+        eprint!("\n{}", USAGE);
+        2
+    } else {
+        1
+    }
+}
+
+/// Release builds use the GUI subsystem and start without a console.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+const USAGE_ERROR: &str = "usage";
+
+/// A `ShadowError` as it comes back from the API.
+#[derive(Debug, Serialize, Deserialize)]
+struct CliError {
+    code: String,
+    message: String,
+}
+
+impl CliError {
+    fn usage(message: impl Into<String>) -> Self {
+        CliError {
+            code: USAGE_ERROR.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ShadowError> for CliError {
+    fn from(e: ShadowError) -> Self {
+        CliError {
+            code: e.code().to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+struct Args {
+    words: Vec<String>,
+    flags: Vec<String>,
+    options: BTreeMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: Vec<String>) -> Result<Args, CliError> {
+        let mut parsed = Args {
+            words: Vec::new(),
+            flags: Vec::new(),
+            options: BTreeMap::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" {
+                parsed.flags.push("--help".to_string());
+                continue;
+            }
+            if !arg.starts_with("--") {
+                parsed.words.push(arg);
+                continue;
+            }
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if FLAGS.contains(&name.as_str()) && value.is_none() {
+                parsed.flags.push(name);
+            } else if OPTIONS.contains(&name.as_str()) {
+                let value = value
+                    .or_else(|| args.next())
+                    .ok_or(CliError::usage(format!("{} needs a value", name)))?;
+                parsed.options.insert(name, value);
+            } else {
+                return Err(CliError::usage(format!("Unknown option {}", name)));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    fn option<T: FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        self.options
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| CliError::usage(format!("Invalid {}: {}", name, value)))
+            })
+            .transpose()
+    }
+
+    fn page(&self) -> Result<PageInfo, CliError> {
+        Ok(PageInfo {
+            page: self.option("--page")?.unwrap_or(1),
+            page_size: self.option("--page-size")?.unwrap_or(DEFAULT_PAGE_SIZE),
+        })
+    }
+}
+
+fn parse_id(id: &str) -> Result<u16, CliError> {
+    id.parse()
+        .map_err(|_| CliError::usage(format!("Invalid id: {}", id)))
+}
+
+/// Proxy URLs from a file, or stdin for `-`, one per line; blank lines and
+/// `#` comments are left out.
+fn read_proxies(path: &str) -> Result<Vec<String>, CliError> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        fs::read_to_string(path)
+    };
+    let text = text.map_err(|e| CliError::usage(format!("Cannot read {}: {}", path, e)))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+enum Command {
+    ProfileList(GroupInfo),
+    ProfileCreate(NewProfileInfo),
+    ProfileDelete(IdInfo),
+    ProfileOpen(OpenInfo),
+    ProfileClose(CloseInfo),
+    GroupList(PageInfo),
+    GroupCreate(NewCommonInfo),
+    GroupDelete(IdInfo),
+    ProxyList(PageInfo),
+    ProxyCheck(ProxyCheckInfo),
+    ProxyImport(ImportInfo),
+    Instances,
+}
+
+impl Command {
+    fn parse(args: &Args) -> Result<Command, CliError> {
+        let words: Vec<&str> = args.words.iter().map(String::as_str).collect();
+        let command = match words.as_slice() {
+            ["profile", "list"] => {
+                let page = args.page()?;
+                Command::ProfileList(GroupInfo {
+                    page: page.page,
+                    page_size: page.page_size,
+                    group_id: args.option("--group")?.unwrap_or(0),
+                })
+            }
+            ["profile", "create", name] => Command::ProfileCreate(NewProfileInfo {
+                name: name.to_string(),
+                group_id: args.option("--group")?,
+                proxy_id: args.option("--proxy")?,
+                remark: args.option("--remark")?,
+            }),
+            ["profile", "delete", id] => Command::ProfileDelete(IdInfo { id: parse_id(id)? }),
+            ["profile", "open", id] => Command::ProfileOpen(OpenInfo {
+                id: parse_id(id)?,
+                headless: args.flag("--headless").then_some(true),
+                overrides: LaunchOverrides {
+                    port: args.option("--port")?,
+                    ..Default::default()
+                },
+            }),
+            ["profile", "close", id] => Command::ProfileClose(CloseInfo {
+                id: parse_id(id)?,
+                options: ShutdownOptions::default(),
+            }),
+            ["group", "list"] => Command::GroupList(args.page()?),
+            ["group", "create", name] => Command::GroupCreate(NewCommonInfo {
+                name: name.to_string(),
+                remark: args.option("--remark")?,
+            }),
+            ["group", "delete", id] => Command::GroupDelete(IdInfo { id: parse_id(id)? }),
+            ["proxy", "list"] => Command::ProxyList(args.page()?),
+            ["proxy", "check", proxy] => Command::ProxyCheck(match proxy.parse() {
+                Ok(id) => ProxyCheckInfo {
+                    id: Some(id),
+                    url: None,
+                },
+                Err(_) => ProxyCheckInfo {
+                    id: None,
+                    url: Some(proxy.to_string()),
+                },
+            }),
+            ["proxy", "import", path] => Command::ProxyImport(ImportInfo {
+                proxies: read_proxies(path)?,
+            }),
+            ["instances"] => Command::Instances,
+            [] => return Err(CliError::usage("No command given")),
+            _ => {
+                return Err(CliError::usage(format!(
+                    "Unknown command: {}",
+                    words.join(" ")
+                )))
+            }
+        };
+        Ok(command)
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    success: bool,
+    data: Option<Value>,
+    msg: Option<String>,
+    error: Option<CliError>,
+}
+
+enum Backend {
+    Api(reqwest::Client),
+    Local,
+}
+
+fn api_url(path: &str) -> String {
+    format!("http://{}:{}{}", API_ADDR.0, API_ADDR.1, path)
+}
+
+fn api_error(e: reqwest::Error) -> CliError {
+    ShadowError::Internal(format!("API request failed: {}", e)).into()
+}
+
+impl Backend {
+    /// The running app or daemon if one answers, otherwise the database and
+    /// instance state directly.
+    async fn connect() -> Result<Backend, CliError> {
+        let client = reqwest::Client::new();
+        let alive = client
+            .get(api_url("/api/status"))
+            .timeout(Duration::from_secs(1))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success());
+        if alive {
+            return Ok(Backend::Api(client));
+        }
+        open_app_data(|_: &str, _| {}).await?;
+        Ok(Backend::Local)
+    }
+
+    /// Posts `body` to the API, or hands it to `local`, the function behind
+    /// that endpoint.
+    async fn call<B, T, F, Fut>(&self, path: &str, body: B, local: F) -> Result<Value, CliError>
+    where
+        B: Serialize,
+        T: Serialize,
+        F: FnOnce(B) -> Fut,
+        Fut: Future<Output = Result<T, ShadowError>>,
+    {
+        match self {
+            Backend::Api(client) => {
+                let response: ApiResponse = client
+                    .post(api_url(path))
+                    .json(&body)
+                    .send()
+                    .await
+                    .map_err(api_error)?
+                    .json()
+                    .await
+                    .map_err(api_error)?;
+                if response.success {
+                    return Ok(response.data.unwrap_or_default());
+                }
+                Err(response.error.unwrap_or(CliError {
+                    code: ShadowError::Internal(String::new()).code().to_string(),
+                    message: response.msg.unwrap_or_default(),
+                }))
+            }
+            Backend::Local => {
+                let data = local(body).await?;
+                serde_json::to_value(data).map_err(|e| ShadowError::Internal(e.to_string()).into())
+            }
+        }
+    }
+}
+
+/// The fields of a `ChromeInstance` worth listing.
+#[derive(Deserialize)]
+struct InstanceRow {
+    id: u16,
+    pid: u32,
+    port: u16,
+    mode: String,
+    proxy: Option<String>,
+    ws: Option<String>,
+}
+
+async fn run(args: &Args) -> Result<(), CliError> {
+    let command = Command::parse(args)?;
+    let backend = Backend::connect().await?;
+    let json = args.flag("--json");
+    let output = |value: Value| -> Result<Option<Value>, CliError> {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&value).unwrap_or_default()
+            );
+            Ok(None)
+        } else {
+            Ok(Some(value))
+        }
+    };
+    match command {
+        Command::ProfileList(info) => {
+            let value = backend
+                .call("/api/browser/list", info, |info| async move {
+                    query_profiles(&info).await
+                })
+                .await?;
+            if let Some(value) = output(value)? {
+                let page: PageResult<ProfileInfo> = decode(value)?;
+                print_table(
+                    &["ID", "NAME", "GROUP", "PROXY", "REMARK"],
+                    page.list.iter().map(|profile| {
+                        vec![
+                            profile.id.to_string(),
+                            profile.name.clone(),
+                            profile.group_name.clone(),
+                            profile.proxy_name.clone(),
+                            profile.remark.clone(),
+                        ]
+                    }),
+                );
+                print_page(&page);
+            }
+        }
+        Command::ProfileCreate(info) => {
+            let value = backend
+                .call("/api/browser/create", info, create_profile)
+                .await?;
+            if let Some(value) = output(value)? {
+                let profile: CommonInfo = decode(value)?;
+                println!("Created profile {} ({})", profile.id, profile.name);
+            }
+        }
+        Command::ProfileDelete(info) => {
+            let value = backend
+                .call("/api/browser/delete", info, delete_profile)
+                .await?;
+            if let Some(value) = output(value)? {
+                let profile: DeletedInfo = decode(value)?;
+                println!("Deleted profile {} ({})", profile.id, profile.name);
+            }
+        }
+        Command::ProfileOpen(info) => {
+            let id = info.id;
+            let value = backend
+                .call("/api/browser/open", info, open_profile)
+                .await?;
+            if let Some(value) = output(value)? {
+                let opened: ChromeOpenInfo = decode(value)?;
+                println!(
+                    "Opened profile {} (PID: {}, port: {})",
+                    id, opened.pid, opened.port
+                );
+                println!("{}", opened.ws);
+            }
+            if let Backend::Local = backend {
+                keep_open(id).await;
+            }
+        }
+        Command::ProfileClose(info) => {
+            let value = backend
+                .call("/api/browser/close", info, close_profile)
+                .await?;
+            if let Some(value) = output(value)? {
+                let closed: ClosedProfileInfo = decode(value)?;
+                println!(
+                    "Closed profile {} (PID: {}, {:?})",
+                    closed.name, closed.pid, closed.step
+                );
+            }
+        }
+        Command::GroupList(info) => {
+            let value = backend
+                .call("/api/group/list", info, |info| async move {
+                    query_groups(&info).await
+                })
+                .await?;
+            if let Some(value) = output(value)? {
+                print_common(decode(value)?);
+            }
+        }
+        Command::GroupCreate(info) => {
+            let value = backend
+                .call("/api/group/create", info, create_group)
+                .await?;
+            if let Some(value) = output(value)? {
+                let group: CommonInfo = decode(value)?;
+                println!("Created group {} ({})", group.id, group.name);
+            }
+        }
+        Command::GroupDelete(info) => {
+            let value = backend
+                .call("/api/group/delete", info, delete_group)
+                .await?;
+            if let Some(value) = output(value)? {
+                let group: DeletedInfo = decode(value)?;
+                println!("Deleted group {} ({})", group.id, group.name);
+            }
+        }
+        Command::ProxyList(info) => {
+            let value = backend
+                .call("/api/proxy/list", info, |info| async move {
+                    query_proxies(&info).await
+                })
+                .await?;
+            if let Some(value) = output(value)? {
+                print_common(decode(value)?);
+            }
+        }
+        Command::ProxyCheck(info) => {
+            let value = backend.call("/api/proxy/check", info, check_proxy).await?;
+            if let Some(value) = output(value)? {
+                let status: ProxyStatus = decode(value)?;
+                println!("Proxy works, {} ms", status.latency.as_millis());
+            }
+        }
+        Command::ProxyImport(info) => {
+            let value = backend
+                .call("/api/proxy/import", info, import_proxies)
+                .await?;
+            if let Some(value) = output(value)? {
+                let result: ImportResult = decode(value)?;
+                for rejected in &result.rejected {
+                    eprintln!("Rejected {}: {}", rejected.proxy, rejected.error);
+                }
+                println!(
+                    "Imported {}, skipped {} already stored, rejected {}",
+                    result.imported.len(),
+                    result.skipped.len(),
+                    result.rejected.len()
+                );
+            }
+        }
+        Command::Instances => {
+            let value = backend
+                .call("/api/browser/active", (), |_| async {
+                    Ok(list_chrome_instances().await)
+                })
+                .await?;
+            if let Some(value) = output(value)? {
+                let instances: Vec<InstanceRow> = decode(value)?;
+                print_table(
+                    &["ID", "PID", "PORT", "MODE", "PROXY", "WS"],
+                    instances.iter().map(|inst| {
+                        vec![
+                            inst.id.to_string(),
+                            inst.pid.to_string(),
+                            inst.port.to_string(),
+                            inst.mode.clone(),
+                            inst.proxy.clone().unwrap_or_default(),
+                            inst.ws.clone().unwrap_or_default(),
+                        ]
+                    }),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Without the app nothing else would keep the proxy relay up or close the
+/// browser later, so stay until it exits and close it on Ctrl-C or SIGTERM.
+async fn keep_open(id: u16) {
+    eprintln!(
+        "No app running, keeping profile {} here until it closes; Ctrl-C closes it.",
+        id
+    );
+    tokio::select! {
+        _ = shutdown_signal() => {
+            let info = CloseInfo { id, options: ShutdownOptions::default() };
+            if let Err(e) = close_profile(info).await {
+                eprintln!("Close profile {} failed: {}", id, e);
+            }
+        }
+        _ = wait_closed(id) => {}
+    }
+}
+
+async fn wait_closed(id: u16) {
+    while !matches!(wait_for_profile(id, Duration::ZERO).await, Ok(None)) {
+        sleep(Duration::from_millis(500)).await;
+    }
+}
+
+fn decode<T: DeserializeOwned>(value: Value) -> Result<T, CliError> {
+    serde_json::from_value(value).map_err(|e| ShadowError::Internal(e.to_string()).into())
+}
+
+fn print_common(page: PageResult<CommonInfo>) {
+    print_table(
+        &["ID", "NAME", "REMARK"],
+        page.list
+            .iter()
+            .map(|row| vec![row.id.to_string(), row.name.clone(), row.remark.clone()]),
+    );
+    print_page(&page);
+}
+
+fn print_page<T>(page: &PageResult<T>) {
+    if page.total as usize > page.list.len() {
+        println!(
+            "Page {} of {}, {} total",
+            page.page,
+            (page.total as usize).div_ceil(page.page_size.max(1) as usize),
+            page.total
+        );
+    }
+}
+
+fn print_table(headers: &[&str], rows: impl Iterator<Item = Vec<String>>) {
+    let rows: Vec<Vec<String>> = rows.collect();
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!(
+        "{}",
+        line(headers.iter().map(|header| header.to_string()).collect())
+    );
+    for row in rows {
+        println!("{}", line(row));
+    }
+}
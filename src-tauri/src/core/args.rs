@@ -0,0 +1,188 @@
+use crate::core::error::ShadowError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Flags the app sets itself. Letting a profile override them would break
+/// DevTools discovery, profile isolation or the proxy relay.
+const OWNED_FLAGS: &[&str] = &[
+    "--user-data-dir",
+    "--remote-debugging-port",
+    "--remote-debugging-pipe",
+    "--remote-debugging-address",
+    "--remote-debugging-io-pipes",
+    "--proxy-server",
+    "--proxy-pac-url",
+    "--no-proxy-server",
+    "--proxy-bypass-list",
+    "--headless",
+];
+
+/// Whether Chrome shows a window. Headless instances use the new headless
+/// mode, which runs the full browser without a UI; virtual display instances
+/// are headful but draw into an Xvfb server (Linux only).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchMode {
+    #[default]
+    Headed,
+    Headless,
+    VirtualDisplay,
+}
+
+impl LaunchMode {
+    pub fn args(self) -> &'static [&'static str] {
+        match self {
+            LaunchMode::Headed | LaunchMode::VirtualDisplay => &[],
+            LaunchMode::Headless => &["--headless=new"],
+        }
+    }
+}
+
+/// Extra command-line flags and environment variables for Chrome, stored per
+/// profile and optionally overridden for a single launch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchExtras {
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+fn flag_name(arg: &str) -> &str {
+    arg.split_once('=').map_or(arg, |(name, _)| name)
+}
+
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with('-') || (cfg!(windows) && arg.starts_with('/'))
+}
+
+// Chrome accepts a switch with one or two leading dashes (or a slash on
+// Windows) and in any case, so flags are compared in this form.
+fn normalized_flag(arg: &str) -> String {
+    let name = flag_name(arg);
+    let name = match name.strip_prefix("--") {
+        Some(name) => name,
+        None if cfg!(windows) && name.starts_with('/') => &name[1..],
+        None => name.strip_prefix('-').unwrap_or(name),
+    };
+    name.to_ascii_lowercase()
+}
+
+impl LaunchExtras {
+    /// Reads the `extra_args` and `extra_env` columns of a profile, both JSON
+    /// and possibly NULL.
+    pub fn from_columns(
+        args: Option<String>,
+        env: Option<String>,
+    ) -> Result<LaunchExtras, ShadowError> {
+        let parse_error = |e: serde_json::Error| ShadowError::InvalidArgument(e.to_string());
+        Ok(LaunchExtras {
+            args: match args.filter(|s| !s.is_empty()) {
+                Some(json) => serde_json::from_str(&json).map_err(parse_error)?,
+                None => Vec::new(),
+            },
+            env: match env.filter(|s| !s.is_empty()) {
+                Some(json) => serde_json::from_str(&json).map_err(parse_error)?,
+                None => BTreeMap::new(),
+            },
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), ShadowError> {
+        for arg in &self.args {
+            if !is_flag(arg) {
+                return Err(ShadowError::InvalidArgument(format!(
+                    "{} is not a command-line flag",
+                    arg
+                )));
+            }
+            let name = normalized_flag(arg);
+            if OWNED_FLAGS
+                .iter()
+                .any(|owned| normalized_flag(owned) == name)
+            {
+                return Err(ShadowError::InvalidArgument(format!(
+                    "{} is managed by the app",
+                    flag_name(arg)
+                )));
+            }
+        }
+        for key in self.env.keys() {
+            if key.is_empty() || key.contains(['=', '\0']) {
+                return Err(ShadowError::InvalidArgument(format!(
+                    "Invalid environment variable name: {:?}",
+                    key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies per-launch `overrides` on top of these settings: a flag
+    /// replaces the one with the same name, a variable the one with the same
+    /// key.
+    pub fn merge(mut self, overrides: LaunchExtras) -> LaunchExtras {
+        self.args.retain(|arg| {
+            !overrides
+                .args
+                .iter()
+                .any(|o| normalized_flag(o) == normalized_flag(arg))
+        });
+        self.args.extend(overrides.args);
+        self.env.extend(overrides.env);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extras(args: &[&str]) -> LaunchExtras {
+        LaunchExtras {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn rejects_owned_flags() {
+        for arg in [
+            "--user-data-dir=/tmp/x",
+            "--remote-debugging-address=0.0.0.0",
+            "--proxy-pac-url=http://example.com/proxy.pac",
+            "--no-proxy-server",
+            "--proxy-bypass-list=*",
+        ] {
+            assert!(extras(&[arg]).validate().is_err(), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn rejects_other_spellings_of_owned_flags() {
+        for arg in [
+            "-user-data-dir=/tmp/x",
+            "--User-Data-Dir=/tmp/x",
+            "-PROXY-SERVER=http://127.0.0.1:1",
+        ] {
+            assert!(extras(&[arg]).validate().is_err(), "{}", arg);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn rejects_slash_flags_on_windows() {
+        assert!(extras(&["/user-data-dir=C:\\x"]).validate().is_err());
+    }
+
+    #[test]
+    fn accepts_other_flags() {
+        assert!(extras(&["--lang=de", "-incognito"]).validate().is_ok());
+        assert!(extras(&["lang=de"]).validate().is_err());
+    }
+
+    #[test]
+    fn overrides_replace_flags_regardless_of_spelling() {
+        let merged = extras(&["--lang=de", "--window-size=800,600"]).merge(extras(&["-LANG=fr"]));
+        assert_eq!(merged.args, ["--window-size=800,600", "-LANG=fr"]);
+    }
+}
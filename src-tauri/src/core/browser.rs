@@ -0,0 +1,449 @@
+use serde::Serialize;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+lazy_static::lazy_static! {
+    static ref INSTALLED_BROWSERS: RwLock<Option<Vec<InstalledBrowser>>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BrowserKind {
+    Chrome,
+    Chromium,
+    Edge,
+    Brave,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BrowserChannel {
+    Stable,
+    Beta,
+    Dev,
+    Canary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledBrowser {
+    pub kind: BrowserKind,
+    pub channel: BrowserChannel,
+    pub version: Option<String>,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserCandidate {
+    pub kind: BrowserKind,
+    pub path: PathBuf,
+}
+
+// Executable names looked up in PATH, in order of preference.
+#[cfg(target_os = "linux")]
+const PATH_NAMES: &[(BrowserKind, &str)] = &[
+    (BrowserKind::Chrome, "google-chrome-stable"),
+    (BrowserKind::Chrome, "google-chrome"),
+    (BrowserKind::Chrome, "google-chrome-beta"),
+    (BrowserKind::Chrome, "google-chrome-unstable"),
+    (BrowserKind::Chromium, "chromium"),
+    (BrowserKind::Chromium, "chromium-browser"),
+    (BrowserKind::Edge, "microsoft-edge-stable"),
+    (BrowserKind::Edge, "microsoft-edge"),
+    (BrowserKind::Edge, "microsoft-edge-beta"),
+    (BrowserKind::Edge, "microsoft-edge-dev"),
+    (BrowserKind::Brave, "brave-browser"),
+    (BrowserKind::Brave, "brave"),
+];
+
+#[cfg(target_os = "windows")]
+const PATH_NAMES: &[(BrowserKind, &str)] = &[
+    (BrowserKind::Chrome, "chrome.exe"),
+    (BrowserKind::Edge, "msedge.exe"),
+    (BrowserKind::Brave, "brave.exe"),
+];
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+const PATH_NAMES: &[(BrowserKind, &str)] = &[
+    (BrowserKind::Chrome, "google-chrome"),
+    (BrowserKind::Chromium, "chromium"),
+];
+
+#[cfg(target_os = "linux")]
+fn platform_candidates() -> Vec<BrowserCandidate> {
+    let mut list: Vec<(BrowserKind, PathBuf)> = [
+        (BrowserKind::Chrome, "/opt/google/chrome/chrome"),
+        (BrowserKind::Chrome, "/opt/google/chrome-beta/chrome"),
+        (BrowserKind::Chrome, "/opt/google/chrome-unstable/chrome"),
+        (BrowserKind::Chromium, "/usr/lib/chromium/chromium"),
+        (
+            BrowserKind::Chromium,
+            "/usr/lib/chromium-browser/chromium-browser",
+        ),
+        (
+            BrowserKind::Chromium,
+            "/usr/lib64/chromium-browser/chromium-browser",
+        ),
+        (BrowserKind::Edge, "/opt/microsoft/msedge/msedge"),
+        (BrowserKind::Edge, "/opt/microsoft/msedge-beta/msedge"),
+        (BrowserKind::Edge, "/opt/microsoft/msedge-dev/msedge"),
+        (BrowserKind::Brave, "/opt/brave.com/brave/brave"),
+        (BrowserKind::Brave, "/opt/brave.com/brave-beta/brave"),
+        (BrowserKind::Chromium, "/snap/bin/chromium"),
+        (BrowserKind::Brave, "/snap/bin/brave"),
+    ]
+    .iter()
+    .map(|(kind, path)| (*kind, PathBuf::from(path)))
+    .collect();
+
+    // Flatpak exports a wrapper script per application id, both system wide
+    // and per user.
+    let mut flatpak_dirs = vec![PathBuf::from("/var/lib/flatpak/exports/bin")];
+    if let Some(home) = env::var_os("HOME") {
+        flatpak_dirs.push(Path::new(&home).join(".local/share/flatpak/exports/bin"));
+    }
+    for dir in flatpak_dirs {
+        for (kind, app_id) in [
+            (BrowserKind::Chrome, "com.google.Chrome"),
+            (BrowserKind::Chromium, "org.chromium.Chromium"),
+            (BrowserKind::Edge, "com.microsoft.Edge"),
+            (BrowserKind::Brave, "com.brave.Browser"),
+        ] {
+            list.push((kind, dir.join(app_id)));
+        }
+    }
+
+    list.into_iter()
+        .map(|(kind, path)| BrowserCandidate { kind, path })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn platform_candidates() -> Vec<BrowserCandidate> {
+    let apps = [
+        (
+            BrowserKind::Chrome,
+            "Google Chrome.app/Contents/MacOS/Google Chrome",
+        ),
+        (
+            BrowserKind::Chrome,
+            "Google Chrome Beta.app/Contents/MacOS/Google Chrome Beta",
+        ),
+        (
+            BrowserKind::Chrome,
+            "Google Chrome Canary.app/Contents/MacOS/Google Chrome Canary",
+        ),
+        (
+            BrowserKind::Chromium,
+            "Chromium.app/Contents/MacOS/Chromium",
+        ),
+        (
+            BrowserKind::Edge,
+            "Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        ),
+        (
+            BrowserKind::Brave,
+            "Brave Browser.app/Contents/MacOS/Brave Browser",
+        ),
+    ];
+    let mut roots = vec![PathBuf::from("/Applications")];
+    if let Some(home) = env::var_os("HOME") {
+        roots.push(Path::new(&home).join("Applications"));
+    }
+    roots
+        .iter()
+        .flat_map(|root| {
+            apps.iter().map(move |(kind, app)| BrowserCandidate {
+                kind: *kind,
+                path: root.join(app),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn platform_candidates() -> Vec<BrowserCandidate> {
+    let apps = [
+        (
+            BrowserKind::Chrome,
+            "Google\\Chrome\\Application\\chrome.exe",
+        ),
+        (
+            BrowserKind::Chrome,
+            "Google\\Chrome Beta\\Application\\chrome.exe",
+        ),
+        (
+            BrowserKind::Chrome,
+            "Google\\Chrome SxS\\Application\\chrome.exe",
+        ),
+        (BrowserKind::Chromium, "Chromium\\Application\\chrome.exe"),
+        (
+            BrowserKind::Edge,
+            "Microsoft\\Edge\\Application\\msedge.exe",
+        ),
+        (
+            BrowserKind::Brave,
+            "BraveSoftware\\Brave-Browser\\Application\\brave.exe",
+        ),
+    ];
+    let roots: Vec<PathBuf> = ["ProgramFiles", "ProgramFiles(x86)", "LocalAppData"]
+        .iter()
+        .filter_map(env::var_os)
+        .map(PathBuf::from)
+        .collect();
+    roots
+        .iter()
+        .flat_map(|root| {
+            apps.iter().map(move |(kind, app)| BrowserCandidate {
+                kind: *kind,
+                path: root.join(app),
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_candidates() -> Vec<BrowserCandidate> {
+    Vec::new()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn path_candidates() -> Vec<BrowserCandidate> {
+    let Some(paths) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let dirs: Vec<PathBuf> = env::split_paths(&paths).collect();
+    PATH_NAMES
+        .iter()
+        .flat_map(|(kind, name)| {
+            dirs.iter().map(move |dir| BrowserCandidate {
+                kind: *kind,
+                path: dir.join(name),
+            })
+        })
+        .collect()
+}
+
+/// Lists every Chromium-family executable found on this machine, in lookup
+/// order. Duplicates reached through symlinks are only reported once.
+pub fn discover_browsers() -> Vec<BrowserCandidate> {
+    let mut found: Vec<BrowserCandidate> = Vec::new();
+    let mut seen: Vec<PathBuf> = Vec::new();
+    for candidate in path_candidates().into_iter().chain(platform_candidates()) {
+        if !is_executable(&candidate.path) {
+            continue;
+        }
+        let real = candidate
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| candidate.path.clone());
+        if seen.contains(&real) {
+            continue;
+        }
+        seen.push(real);
+        found.push(candidate);
+    }
+    found
+}
+
+/// Resolves the browser executable to launch. An explicitly configured path
+/// wins, then the `CHROME_PATH` environment variable, then the first browser
+/// found by [`discover_browsers`].
+pub fn find_browser(preferred: Option<&str>) -> Option<PathBuf> {
+    let overrides = preferred
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(env::var_os("CHROME_PATH").map(PathBuf::from));
+    for path in overrides {
+        if is_executable(&path) {
+            return Some(path);
+        }
+        eprintln!("Configured browser path {:?} is not executable.", path);
+    }
+    discover_browsers()
+        .into_iter()
+        .next()
+        .map(|candidate| candidate.path)
+}
+
+/// How long `<browser> --version` may take before the version counts as
+/// unknown, so a hanging wrapper on PATH cannot stall discovery.
+#[cfg(not(target_os = "windows"))]
+const VERSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[cfg(not(target_os = "windows"))]
+fn detect_version(path: &Path) -> Option<String> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    let mut child = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    // Read on a thread; whatever the browser forks may keep the pipe open
+    // after it exits.
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        let _ = tx.send(output);
+    });
+    let deadline = Instant::now() + VERSION_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        return None;
+    }
+    let output = rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .ok()?;
+    let version = String::from_utf8_lossy(&output).trim().to_string();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+// On Windows `chrome.exe --version` opens a browser window instead of
+// printing, so the version is taken from the versioned directory that every
+// Chromium installer puts next to the executable.
+#[cfg(target_os = "windows")]
+fn detect_version(path: &Path) -> Option<String> {
+    let dir = path.parent()?;
+    let mut versions: Vec<Vec<u32>> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let parts: Option<Vec<u32>> = name.split('.').map(|p| p.parse().ok()).collect();
+            parts.filter(|p| p.len() == 4)
+        })
+        .collect();
+    versions.sort();
+    let version = versions.pop()?;
+    let version = version
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(".");
+    let name = path.file_stem()?.to_string_lossy();
+    Some(format!("{} {}", name, version))
+}
+
+fn detect_channel(path: &Path, version: Option<&str>) -> BrowserChannel {
+    let haystack = format!("{} {}", path.display(), version.unwrap_or_default()).to_lowercase();
+    let tokens: Vec<&str> = haystack
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .collect();
+    if tokens
+        .iter()
+        .any(|t| matches!(*t, "canary" | "sxs" | "nightly"))
+    {
+        BrowserChannel::Canary
+    } else if tokens.iter().any(|t| matches!(*t, "dev" | "unstable")) {
+        BrowserChannel::Dev
+    } else if tokens.contains(&"beta") {
+        BrowserChannel::Beta
+    } else {
+        BrowserChannel::Stable
+    }
+}
+
+/// Returns the registry of installed browsers, probing every discovered
+/// executable for its version on first use or when `refresh` is set.
+pub fn installed_browsers(refresh: bool) -> Vec<InstalledBrowser> {
+    if !refresh {
+        if let Some(browsers) = INSTALLED_BROWSERS.read().unwrap().as_ref() {
+            return browsers.clone();
+        }
+    }
+    let browsers: Vec<InstalledBrowser> = discover_browsers()
+        .into_iter()
+        .map(|candidate| {
+            let version = detect_version(&candidate.path);
+            InstalledBrowser {
+                kind: candidate.kind,
+                channel: detect_channel(&candidate.path, version.as_deref()),
+                version,
+                path: candidate.path.to_string_lossy().to_string(),
+            }
+        })
+        .collect();
+    eprintln!("Found {} installed browsers.", browsers.len());
+    *INSTALLED_BROWSERS.write().unwrap() = Some(browsers.clone());
+    browsers
+}
+
+/// Resolves a browser pinned on a profile. Unlike [`find_browser`] this never
+/// falls back to another executable, so a profile keeps running the exact
+/// build it was configured with.
+pub fn pinned_browser(path: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(path);
+    if is_executable(&path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(name: &str, body: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("shadow-browser-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_version_output() {
+        let path = script("chrome", "echo 'Google Chrome 126.0.6478.126'");
+        assert_eq!(
+            detect_version(&path).as_deref(),
+            Some("Google Chrome 126.0.6478.126")
+        );
+    }
+
+    #[test]
+    fn hanging_version_is_unknown() {
+        let path = script("hang", "sleep 30");
+        let start = std::time::Instant::now();
+        assert_eq!(detect_version(&path), None);
+        assert!(start.elapsed() < VERSION_TIMEOUT * 2);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::core::error::ShadowError;
+use sqlx::migrate::{Migration, MigrationType, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::borrow::Cow;
+use std::path::Path;
+
+pub const DB_URL: &str = "sqlite:shadow.db";
+const DB_FILE: &str = "shadow.db";
+
+/// Schema migrations as `(version, description, sql)`. The GUI hands them to
+/// tauri-plugin-sql and the daemon runs them itself; both record them the
+/// same way, so either can open a database the other created.
+pub const MIGRATIONS: &[(i64, &str, &str)] = &[
+    (1, "table init", include_str!("../../migrations/1.sql")),
+    (
+        2,
+        "profile browser path",
+        include_str!("../../migrations/2.sql"),
+    ),
+    (
+        3,
+        "profile restart policy",
+        include_str!("../../migrations/3.sql"),
+    ),
+    (
+        4,
+        "profile extra args",
+        include_str!("../../migrations/4.sql"),
+    ),
+    (
+        5,
+        "profile launch mode",
+        include_str!("../../migrations/5.sql"),
+    ),
+];
+
+/// Opens the database tauri-plugin-sql keeps in the app config dir and
+/// brings its schema up to date.
+pub async fn open(config_dir: &Path) -> Result<Pool<Sqlite>, ShadowError> {
+    std::fs::create_dir_all(config_dir).map_err(|e| ShadowError::Database(e.to_string()))?;
+    let options = SqliteConnectOptions::new()
+        .filename(config_dir.join(DB_FILE))
+        .create_if_missing(true);
+    let pool = SqlitePoolOptions::new().connect_with(options).await?;
+    let migrations = MIGRATIONS
+        .iter()
+        .map(|(version, description, sql)| {
+            Migration::new(
+                *version,
+                Cow::Borrowed(*description),
+                MigrationType::Simple,
+                Cow::Borrowed(*sql),
+                false,
+            )
+        })
+        .collect::<Vec<_>>();
+    let migrator = Migrator {
+        migrations: Cow::Owned(migrations),
+        ..Migrator::DEFAULT
+    };
+    migrator
+        .run(&pool)
+        .await
+        .map_err(|e| ShadowError::Database(e.to_string()))?;
+    Ok(pool)
+}
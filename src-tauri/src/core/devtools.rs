@@ -0,0 +1,152 @@
+use crate::core::process::ChromeProcess;
+use serde::Serialize;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::sleep;
+
+pub const DEFAULT_STARTUP_TIMEOUT_MS: u64 = 30_000;
+
+const DEVTOOLS_PORT_FILE: &str = "DevToolsActivePort";
+
+/// Chrome writes DevToolsActivePort right after binding, so a fixed port that
+/// accepts connections this long without the file belongs to someone else.
+const FOREIGN_PORT_GRACE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DevToolsEndpoint {
+    pub port: u16,
+    pub ws: String,
+}
+
+/// Why Chrome did not come up with a usable DevTools endpoint.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum StartupError {
+    /// The process exited before DevTools was reachable.
+    Crashed { code: Option<i32> },
+    /// Chrome is running but never reported a DevTools port.
+    Timeout { waited_ms: u64 },
+    /// Chrome reported a port but nothing accepted connections on it.
+    PortNotOpened { port: u16 },
+    /// The requested port was taken by another process, so Chrome could not
+    /// bind it.
+    PortInUse { port: u16 },
+}
+
+impl Display for StartupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartupError::Crashed { code: Some(code) } => {
+                write!(f, "Chrome exited during startup with code {}", code)
+            }
+            StartupError::Crashed { code: None } => {
+                write!(f, "Chrome was terminated during startup")
+            }
+            StartupError::Timeout { waited_ms } => {
+                write!(f, "Chrome did not start within {}ms", waited_ms)
+            }
+            StartupError::PortNotOpened { port } => {
+                write!(f, "Chrome never opened DevTools port {}", port)
+            }
+            StartupError::PortInUse { port } => {
+                write!(f, "DevTools port {} is used by another process", port)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StartupError {}
+
+fn port_file(user_dir: &Path) -> PathBuf {
+    user_dir.join(DEVTOOLS_PORT_FILE)
+}
+
+/// Removes a `DevToolsActivePort` left behind by a previous run, so a stale
+/// port is never mistaken for the new instance's.
+pub fn clear_port_file(user_dir: &Path) {
+    let _ = fs::remove_file(port_file(user_dir));
+}
+
+/// Chrome writes the bound port on the first line and the browser target
+/// path on the second once the DevTools server is listening.
+fn read_port_file(user_dir: &Path) -> Option<(u16, String)> {
+    let content = fs::read_to_string(port_file(user_dir)).ok()?;
+    let mut lines = content.lines();
+    let port = lines.next()?.trim().parse().ok()?;
+    let path = lines.next()?.trim().to_string();
+    if path.is_empty() {
+        return None;
+    }
+    Some((port, path))
+}
+
+/// Waits until Chrome's DevTools endpoint accepts connections. Works for both
+/// `--remote-debugging-port=0` and a fixed `requested_port`, and gives up
+/// early when the process dies or the fixed port turns out to be taken.
+pub async fn wait_for_devtools(
+    process: &ChromeProcess,
+    user_dir: &Path,
+    requested_port: Option<u16>,
+    timeout: Duration,
+) -> Result<DevToolsEndpoint, StartupError> {
+    let start = Instant::now();
+    let mut reported_port = None;
+    let mut foreign_since = None;
+    loop {
+        if let Some(exit) = process.exit_info() {
+            return Err(StartupError::Crashed { code: exit.code });
+        }
+        if let Some((port, path)) = read_port_file(user_dir) {
+            reported_port = Some(port);
+            if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                eprintln!("Chrome Started, port: {} used.", port);
+                return Ok(DevToolsEndpoint {
+                    port,
+                    ws: format!("ws://127.0.0.1:{}{}", port, path),
+                });
+            }
+        } else if let Some(port) = requested_port {
+            if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                let since = *foreign_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= FOREIGN_PORT_GRACE {
+                    return Err(StartupError::PortInUse { port });
+                }
+            } else {
+                foreign_since = None;
+            }
+        }
+        if start.elapsed() >= timeout {
+            return Err(match reported_port {
+                Some(port) => StartupError::PortNotOpened { port },
+                None => StartupError::Timeout {
+                    waited_ms: timeout.as_millis() as u64,
+                },
+            });
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Asks a DevTools server for its browser websocket, e.g. to check that a
+/// port recorded earlier still belongs to a live Chrome.
+pub async fn browser_ws(port: u16) -> Option<String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .ok()?;
+    let version: serde_json::Value = client
+        .get(format!("http://127.0.0.1:{}/json/version", port))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    version
+        .get("webSocketDebuggerUrl")?
+        .as_str()
+        .map(str::to_string)
+}
@@ -0,0 +1,224 @@
+use crate::core::error::ShadowError;
+use crate::core::process::ChromeProcess;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Display numbers below this are left to real X servers.
+const FIRST_DISPLAY: u32 = 99;
+const LAST_DISPLAY: u32 = 599;
+#[cfg(target_os = "linux")]
+const XVFB_SCREEN: &str = "1920x1080x24";
+#[cfg(target_os = "linux")]
+const XVFB_STARTUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    pub static ref VIRTUAL_DISPLAYS: DisplayManager = DisplayManager::default();
+}
+
+#[derive(Debug)]
+struct VirtualDisplay {
+    pool: Option<String>,
+    users: usize,
+    xvfb: Arc<ChromeProcess>,
+}
+
+/// Xvfb servers started for instances launched in virtual display mode. An
+/// instance gets a display of its own unless it names a pool, in which case
+/// it shares the pool's display. A display is stopped once the last
+/// [`DisplayLease`] on it is dropped.
+#[derive(Debug, Default)]
+pub struct DisplayManager {
+    displays: Mutex<BTreeMap<u32, VirtualDisplay>>,
+}
+
+#[derive(Debug)]
+pub struct DisplayLease {
+    number: u32,
+    manager: &'static DisplayManager,
+}
+
+fn socket_path(number: u32) -> PathBuf {
+    PathBuf::from(format!("/tmp/.X11-unix/X{}", number))
+}
+
+fn lock_path(number: u32) -> PathBuf {
+    PathBuf::from(format!("/tmp/.X{}-lock", number))
+}
+
+impl DisplayManager {
+    /// Returns a display for a new instance, starting Xvfb if needed. Blocks
+    /// while Xvfb starts up.
+    pub fn acquire(&'static self, pool: Option<&str>) -> Result<DisplayLease, ShadowError> {
+        let mut displays = self.displays.lock().unwrap();
+        if let Some(pool) = pool {
+            let shared = displays
+                .iter_mut()
+                .find(|(_, d)| d.pool.as_deref() == Some(pool) && d.xvfb.is_running());
+            if let Some((&number, display)) = shared {
+                display.users += 1;
+                return Ok(DisplayLease {
+                    number,
+                    manager: self,
+                });
+            }
+        }
+        let number = (FIRST_DISPLAY..=LAST_DISPLAY)
+            .find(|n| {
+                !displays.contains_key(n) && !lock_path(*n).exists() && !socket_path(*n).exists()
+            })
+            .ok_or(ShadowError::DisplayFailed(
+                "no free display number".to_string(),
+            ))?;
+        let xvfb = start_xvfb(number)?;
+        eprintln!("Xvfb (PID: {}) started on :{}", xvfb.pid(), number);
+        displays.insert(
+            number,
+            VirtualDisplay {
+                pool: pool.map(str::to_string),
+                users: 1,
+                xvfb,
+            },
+        );
+        Ok(DisplayLease {
+            number,
+            manager: self,
+        })
+    }
+
+    /// Takes over a display a previous run of the app started, if its Xvfb
+    /// is still running.
+    pub fn adopt(
+        &'static self,
+        number: u32,
+        pool: Option<&str>,
+        pid: u32,
+    ) -> Result<DisplayLease, ShadowError> {
+        let mut displays = self.displays.lock().unwrap();
+        if let Some(display) = displays.get_mut(&number) {
+            display.users += 1;
+        } else {
+            if !socket_path(number).exists() {
+                return Err(ShadowError::DisplayFailed(format!(
+                    "display :{} is gone",
+                    number
+                )));
+            }
+            if !is_xvfb_for(pid, number) {
+                return Err(ShadowError::DisplayFailed(format!(
+                    "PID {} is no longer Xvfb on :{}",
+                    pid, number
+                )));
+            }
+            let xvfb =
+                ChromeProcess::adopt(pid).map_err(|e| ShadowError::DisplayFailed(e.to_string()))?;
+            displays.insert(
+                number,
+                VirtualDisplay {
+                    pool: pool.map(str::to_string),
+                    users: 1,
+                    xvfb,
+                },
+            );
+        }
+        Ok(DisplayLease {
+            number,
+            manager: self,
+        })
+    }
+
+    fn release(&self, number: u32) {
+        let mut displays = self.displays.lock().unwrap();
+        let Some(display) = displays.get_mut(&number) else {
+            return;
+        };
+        display.users -= 1;
+        if display.users == 0 {
+            let display = displays.remove(&number).unwrap();
+            display.xvfb.mark_closing();
+            match display.xvfb.kill_tree() {
+                Ok(()) => eprintln!("Xvfb on :{} stopped.", number),
+                Err(e) => eprintln!("Stop Xvfb on :{} failed: {}", number, e),
+            }
+        }
+    }
+}
+
+impl DisplayLease {
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// The value for `DISPLAY`.
+    pub fn name(&self) -> String {
+        format!(":{}", self.number)
+    }
+
+    pub fn xvfb_pid(&self) -> Option<u32> {
+        let displays = self.manager.displays.lock().unwrap();
+        displays.get(&self.number).map(|d| d.xvfb.pid())
+    }
+}
+
+impl Drop for DisplayLease {
+    fn drop(&mut self) {
+        self.manager.release(self.number);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn start_xvfb(number: u32) -> Result<Arc<ChromeProcess>, ShadowError> {
+    use std::process::{Command, Stdio};
+    use std::time::Instant;
+
+    let mut cmd = Command::new("Xvfb");
+    cmd.arg(format!(":{}", number))
+        .args(["-screen", "0", XVFB_SCREEN, "-nolisten", "tcp"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let xvfb = ChromeProcess::spawn(&mut cmd)
+        .map_err(|e| ShadowError::DisplayFailed(format!("start Xvfb: {}", e)))?;
+    let start = Instant::now();
+    // Xvfb creates its socket once it accepts clients.
+    while !socket_path(number).exists() {
+        if !xvfb.is_running() {
+            return Err(ShadowError::DisplayFailed(format!(
+                "Xvfb exited while starting on :{}",
+                number
+            )));
+        }
+        if start.elapsed() >= XVFB_STARTUP_TIMEOUT {
+            let _ = xvfb.kill_tree();
+            return Err(ShadowError::DisplayFailed(format!(
+                "Xvfb did not start on :{} in time",
+                number
+            )));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    Ok(xvfb)
+}
+
+// The stored pid may have been reused since, so it is only adopted (and
+// later killed) if it is still the Xvfb serving that display.
+#[cfg(target_os = "linux")]
+fn is_xvfb_for(pid: u32, number: u32) -> bool {
+    let Some(args) = crate::core::process::command_line(pid) else {
+        return false;
+    };
+    let display = format!(":{}", number);
+    args.first()
+        .and_then(|program| std::path::Path::new(program).file_name())
+        .is_some_and(|name| name == "Xvfb")
+        && args.contains(&display)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_xvfb_for(_pid: u32, _number: u32) -> bool {
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+fn start_xvfb(_number: u32) -> Result<Arc<ChromeProcess>, ShadowError> {
+    Err(ShadowError::UnsupportedOs)
+}
@@ -0,0 +1,118 @@
+use crate::core::devtools::StartupError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt::Display;
+
+/// Every failure a Tauri command or the HTTP API can report. Both serialize
+/// it as `{ "code": ..., "message": ... }`; `code` is stable and meant to be
+/// matched on by scripts, `message` is for humans.
+#[derive(Debug, Clone)]
+pub enum ShadowError {
+    UnsupportedOs,
+    ExecutableMissing {
+        path: Option<String>,
+    },
+    ProfileNotFound {
+        id: u16,
+    },
+    /// The profile's user data dir is already used by a running browser.
+    ProfileLocked {
+        user_dir: String,
+    },
+    /// Extra Chrome flags or environment variables were rejected.
+    InvalidArgument(String),
+    InstanceNotFound,
+    PortExhausted,
+    /// No virtual display could be set up for the instance.
+    DisplayFailed(String),
+    SpawnFailed(String),
+    Startup(StartupError),
+    ShutdownFailed(String),
+    InvalidProxy(String),
+    ProxyUnreachable(String),
+    ProxyRelayFailed(String),
+    ProxyRelayNotFound,
+    Database(String),
+    Internal(String),
+}
+
+impl ShadowError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ShadowError::UnsupportedOs => "unsupported_os",
+            ShadowError::ExecutableMissing { .. } => "executable_missing",
+            ShadowError::ProfileNotFound { .. } => "profile_not_found",
+            ShadowError::ProfileLocked { .. } => "profile_locked",
+            ShadowError::InvalidArgument(_) => "invalid_argument",
+            ShadowError::InstanceNotFound => "instance_not_found",
+            ShadowError::PortExhausted => "port_exhausted",
+            ShadowError::DisplayFailed(_) => "display_failed",
+            ShadowError::SpawnFailed(_) => "spawn_failed",
+            ShadowError::Startup(StartupError::Crashed { .. }) => "startup_crashed",
+            ShadowError::Startup(StartupError::Timeout { .. }) => "startup_timeout",
+            ShadowError::Startup(StartupError::PortNotOpened { .. }) => "port_not_opened",
+            ShadowError::Startup(StartupError::PortInUse { .. }) => "port_in_use",
+            ShadowError::ShutdownFailed(_) => "shutdown_failed",
+            ShadowError::InvalidProxy(_) => "invalid_proxy",
+            ShadowError::ProxyUnreachable(_) => "proxy_unreachable",
+            ShadowError::ProxyRelayFailed(_) => "proxy_relay_failed",
+            ShadowError::ProxyRelayNotFound => "proxy_relay_not_found",
+            ShadowError::Database(_) => "db_error",
+            ShadowError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl Display for ShadowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShadowError::UnsupportedOs => write!(f, "Os not supported"),
+            ShadowError::ExecutableMissing { path: Some(path) } => {
+                write!(f, "Pinned browser not found: {}", path)
+            }
+            ShadowError::ExecutableMissing { path: None } => {
+                write!(f, "No Chrome, Chromium, Edge or Brave executable found")
+            }
+            ShadowError::ProfileNotFound { id } => write!(f, "Profile {} not found", id),
+            ShadowError::ProfileLocked { user_dir } => {
+                write!(f, "Profile {} is already in use by a browser", user_dir)
+            }
+            ShadowError::InvalidArgument(e) => write!(f, "Invalid argument: {}", e),
+            ShadowError::InstanceNotFound => write!(f, "Instance not found"),
+            ShadowError::PortExhausted => write!(f, "No port useable"),
+            ShadowError::DisplayFailed(e) => write!(f, "Virtual display failed: {}", e),
+            ShadowError::SpawnFailed(e) => write!(f, "Chrome launch failed: {}", e),
+            ShadowError::Startup(e) => write!(f, "Chrome launch failed: {}", e),
+            ShadowError::ShutdownFailed(e) => write!(f, "Close Chrome failed: {}", e),
+            ShadowError::InvalidProxy(e) => write!(f, "Invalid proxy: {}", e),
+            ShadowError::ProxyUnreachable(e) => write!(f, "Proxy unreachable: {}", e),
+            ShadowError::ProxyRelayFailed(e) => write!(f, "Proxy relay failed: {}", e),
+            ShadowError::ProxyRelayNotFound => write!(f, "Server not found"),
+            ShadowError::Database(e) => write!(f, "Database error: {}", e),
+            ShadowError::Internal(e) => write!(f, "Internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ShadowError {}
+
+impl Serialize for ShadowError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ShadowError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<StartupError> for ShadowError {
+    fn from(e: StartupError) -> Self {
+        ShadowError::Startup(e)
+    }
+}
+
+impl From<sqlx::Error> for ShadowError {
+    fn from(e: sqlx::Error) -> Self {
+        ShadowError::Database(e.to_string())
+    }
+}
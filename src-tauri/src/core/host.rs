@@ -0,0 +1,97 @@
+use crate::core::error::ShadowError;
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Bundle identifier from tauri.conf.json; processes without the webview use
+/// it to find the same directories as the GUI.
+pub const IDENTIFIER: &str = "com.shadow.app";
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Receives the events the GUI listens to, such as `chrome-started` and
+/// `chrome-closed`.
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &str, payload: serde_json::Value);
+}
+
+impl<F: Fn(&str, serde_json::Value) + Send + Sync> EventSink for F {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        self(event, payload)
+    }
+}
+
+/// Hands out the pool for the profile database, which the GUI only has once
+/// the frontend has loaded it.
+pub trait Database: Send + Sync {
+    fn pool(&self) -> BoxFuture<'_, Result<Pool<Sqlite>, ShadowError>>;
+}
+
+impl Database for Pool<Sqlite> {
+    fn pool(&self) -> BoxFuture<'_, Result<Pool<Sqlite>, ShadowError>> {
+        Box::pin(async move { Ok(self.clone()) })
+    }
+}
+
+/// What the instance manager needs from the process it runs in: the GUI app,
+/// the headless daemon or another tool embedding it.
+pub struct Host {
+    events: Box<dyn EventSink>,
+    database: Box<dyn Database>,
+    data_dir: PathBuf,
+}
+
+static HOST: OnceLock<Host> = OnceLock::new();
+
+pub fn init(host: Host) {
+    let _ = HOST.set(host);
+}
+
+pub fn host() -> &'static Host {
+    HOST.get().expect("host not initialized")
+}
+
+impl Host {
+    /// `data_dir` holds the profiles, the settings and the instance state.
+    pub fn new(
+        events: impl EventSink + 'static,
+        database: impl Database + 'static,
+        data_dir: PathBuf,
+    ) -> Host {
+        Host {
+            events: Box::new(events),
+            database: Box::new(database),
+            data_dir,
+        }
+    }
+
+    pub fn emit<S: Serialize>(&self, event: &str, payload: S) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => self.events.emit(event, payload),
+            Err(e) => eprintln!("Serialize {} event failed: {}", event, e),
+        }
+    }
+
+    pub async fn db(&self) -> Result<Pool<Sqlite>, ShadowError> {
+        self.database.pool().await
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn profiles_dir(&self) -> PathBuf {
+        self.data_dir.join("profiles")
+    }
+
+    /// The Chrome path set on the settings page, read from the file the
+    /// frontend's store persists to.
+    pub fn configured_browser(&self) -> Option<String> {
+        let json = fs::read(self.data_dir.join(SETTINGS_FILE)).ok()?;
+        let settings: serde_json::Value = serde_json::from_slice(&json).ok()?;
+        settings.get("chrome_path")?.as_str().map(str::to_string)
+    }
+}
@@ -0,0 +1,558 @@
+use crate::core::args::{LaunchExtras, LaunchMode};
+use crate::core::browser::{find_browser, pinned_browser};
+use crate::core::devtools::{clear_port_file, wait_for_devtools, DevToolsEndpoint, StartupError};
+use crate::core::display::{DisplayLease, VIRTUAL_DISPLAYS};
+use crate::core::error::ShadowError;
+use crate::core::host::host;
+use crate::core::ports::{PortLease, DEBUG_PORTS};
+use crate::core::process::{ChromeProcess, ExitInfo};
+use crate::core::proxy_manager::{ProxyChain, RELAYS};
+use crate::core::reattach::save_instances;
+use crate::core::restart::{RestartPolicy, STABLE_RUN};
+use crate::core::shutdown::{shutdown_chrome, ShutdownOptions, ShutdownStep};
+use futures_util::future::join_all;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time::sleep;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChromeInstance {
+    pub id: u16,
+    pub pid: u32,
+    pub user_dir: String,
+    pub port: u16,
+    pub os: String,
+    pub proxy: Option<String>,
+    pub ws: Option<String>,
+    pub mode: LaunchMode,
+    /// How many times in a row this profile was restarted after exiting.
+    pub restarts: u32,
+    #[serde(skip)]
+    pub process: Arc<ChromeProcess>,
+    #[serde(skip)]
+    pub spec: Arc<LaunchSpec>,
+    /// Held for as long as the instance is tracked; the port is released
+    /// when the instance is dropped, however it exited.
+    #[serde(skip)]
+    pub lease: Option<Arc<PortLease>>,
+    /// `DISPLAY` of the Xvfb server a virtual display instance draws into.
+    pub display: Option<String>,
+    /// Keeps the Xvfb server up while the instance is tracked.
+    #[serde(skip)]
+    pub display_lease: Option<Arc<DisplayLease>>,
+}
+
+#[derive(Clone, Serialize)]
+struct ChromeStarted {
+    user_dir: String,
+    pid: u32,
+    ws: String,
+}
+
+/// Everything needed to start an instance again exactly the same way.
+#[derive(Debug, Clone)]
+pub struct LaunchSpec {
+    pub(crate) id: u16,
+    pub(crate) user_dir: String,
+    pub(crate) port: Option<u16>,
+    pub(crate) proxy: Option<String>,
+    /// Upstream of the local relay `proxy` points at, to recreate the relay
+    /// if the app restarts while Chrome keeps running.
+    pub(crate) relay: Option<ProxyChain>,
+    pub(crate) chrome_path: PathBuf,
+    pub(crate) mode: LaunchMode,
+    /// Instances naming the same pool share one virtual display.
+    pub(crate) display_pool: Option<String>,
+    /// Profile flags and environment with the launch overrides applied.
+    pub(crate) extras: LaunchExtras,
+    pub(crate) startup_timeout: Duration,
+    pub(crate) restart: RestartPolicy,
+}
+
+#[derive(Clone, Serialize)]
+struct ChromeRestarted {
+    user_dir: String,
+    old_pid: u32,
+    pid: u32,
+    ws: Option<String>,
+    reason: CloseReason,
+    attempt: u32,
+}
+
+/// Why an instance went away, as reported in `chrome-closed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CloseReason {
+    UserClosed,
+    Crashed,
+    KilledByApp,
+    StartupFailed,
+}
+
+#[derive(Clone, Serialize)]
+struct ChromeStoped {
+    pid: u32,
+    proxy: Option<String>,
+    reason: CloseReason,
+    #[serde(flatten)]
+    exit: ExitInfo,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ChromeClosed {
+    pub pid: u32,
+    pub os: String,
+    pub step: ShutdownStep,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ChromeLaunchInfo {
+    pub pid: u32,
+    pub port: u16,
+    pub ws: Option<String>,
+    pub os: String,
+}
+
+/// How many ports are tried when Chrome cannot bind the requested one.
+const PORT_BIND_ATTEMPTS: u32 = 3;
+
+lazy_static::lazy_static! {
+    pub(crate) static ref CHROME_INSTANCES: Arc<Mutex<HashMap<u32, ChromeInstance>>> = Arc::new(Mutex::new(HashMap::new()));
+    /// User data dirs of instances being started, claimed while holding the
+    /// `CHROME_INSTANCES` lock.
+    static ref STARTING_DIRS: std::sync::Mutex<HashSet<String>> = std::sync::Mutex::new(HashSet::new());
+}
+
+/// Claim on a user data dir while its instance starts or its profile is
+/// deleted, released on drop.
+pub(crate) struct StartingDir(String);
+
+impl StartingDir {
+    /// Claims `user_dir` unless a tracked or starting instance already uses it.
+    pub(crate) async fn claim(user_dir: &str) -> Option<StartingDir> {
+        let instances = CHROME_INSTANCES.lock().await;
+        let mut starting = STARTING_DIRS.lock().unwrap();
+        if instances.values().any(|inst| inst.user_dir == user_dir)
+            || !starting.insert(user_dir.to_string())
+        {
+            return None;
+        }
+        Some(StartingDir(user_dir.to_string()))
+    }
+}
+
+impl Drop for StartingDir {
+    fn drop(&mut self) {
+        STARTING_DIRS.lock().unwrap().remove(&self.0);
+    }
+}
+
+pub async fn list_chrome_instances() -> Vec<ChromeInstance> {
+    let instances = CHROME_INSTANCES.lock().await;
+    let mut instances = instances.clone();
+    // Exits are removed by the monitors, so only instances still starting
+    // need to be left out; no need to probe every DevTools port.
+    instances.retain(|_, inst| inst.ws.is_some() && inst.process.is_running());
+    instances.values().cloned().collect()
+}
+
+pub(crate) async fn monitor_chrome(
+    process: Arc<ChromeProcess>,
+    spec: LaunchSpec,
+    port: u16,
+    restarts: u32,
+) {
+    let pid = process.pid();
+    let started = Instant::now();
+    eprintln!("Chrome (PID: {}) started, waiting for exit.", pid);
+    let exit = process.wait().await;
+    let reason = if process.is_closing() {
+        CloseReason::KilledByApp
+    } else if exit.code == Some(0) || exit == ExitInfo::default() {
+        // Adopted processes exit with an unknown status; don't treat that
+        // as a crash.
+        CloseReason::UserClosed
+    } else {
+        CloseReason::Crashed
+    };
+    eprintln!("Chrome (PID: {}) closed: {:?} {:?}", pid, reason, exit);
+    {
+        // The pid may already belong to a newer instance if this one was
+        // closed through close_instance.
+        let mut instances = CHROME_INSTANCES.lock().await;
+        if instances
+            .get(&pid)
+            .is_some_and(|inst| Arc::ptr_eq(&inst.process, &process))
+        {
+            instances.remove(&pid);
+        }
+    }
+    save_instances().await;
+    if spec.restart.should_restart(reason) {
+        let restarts = if started.elapsed() >= STABLE_RUN {
+            0
+        } else {
+            restarts
+        };
+        if restart_chrome(&spec, pid, port, reason, restarts).await {
+            return;
+        }
+    }
+    release_relay(spec.proxy.as_deref()).await;
+    host().emit(
+        "chrome-closed",
+        ChromeStoped {
+            pid,
+            proxy: spec.proxy,
+            reason,
+            exit,
+        },
+    );
+}
+
+/// Relaunches an exited instance on the same port with the same proxy relay
+/// and arguments, backing off between attempts. Returns whether it came back.
+async fn restart_chrome(
+    spec: &LaunchSpec,
+    old_pid: u32,
+    port: u16,
+    reason: CloseReason,
+    mut restarts: u32,
+) -> bool {
+    let mut spec = spec.clone();
+    spec.port = Some(port);
+    while restarts < spec.restart.max_retries {
+        restarts += 1;
+        let delay = spec.restart.backoff(restarts);
+        eprintln!(
+            "Restarting Chrome (PID: {}) in {:?}, attempt {}/{}",
+            old_pid, delay, restarts, spec.restart.max_retries
+        );
+        sleep(delay).await;
+        match relaunch(spec.clone(), restarts).await {
+            Ok(info) => {
+                host().emit(
+                    "chrome-restarted",
+                    ChromeRestarted {
+                        user_dir: spec.user_dir.clone(),
+                        old_pid,
+                        pid: info.pid,
+                        ws: info.ws,
+                        reason,
+                        attempt: restarts,
+                    },
+                );
+                return true;
+            }
+            Err(e) => eprintln!("Restart of Chrome (PID: {}) failed: {}", old_pid, e),
+        }
+    }
+    false
+}
+
+// Boxed so the monitor spawned by start_instance can start an instance
+// again without an infinitely sized future.
+fn relaunch(
+    spec: LaunchSpec,
+    restarts: u32,
+) -> Pin<Box<dyn Future<Output = Result<ChromeLaunchInfo, ShadowError>> + Send>> {
+    Box::pin(start_instance(spec, restarts, false))
+}
+
+/// Waits until the tracked instance of profile `id`, if any, reports its
+/// DevTools websocket. `None` means the profile is not running (anymore).
+pub async fn wait_for_profile(
+    id: u16,
+    timeout: Duration,
+) -> Result<Option<ChromeLaunchInfo>, StartupError> {
+    let start = std::time::Instant::now();
+    loop {
+        let info = CHROME_INSTANCES
+            .lock()
+            .await
+            .values()
+            .find(|inst| inst.id == id)
+            .map(|inst| ChromeLaunchInfo {
+                pid: inst.pid,
+                port: inst.port,
+                ws: inst.ws.clone(),
+                os: inst.os.clone(),
+            });
+        match info {
+            Some(ChromeLaunchInfo { ws: None, .. }) => {}
+            info => return Ok(info),
+        }
+        if start.elapsed() >= timeout {
+            return Err(StartupError::Timeout {
+                waited_ms: timeout.as_millis() as u64,
+            });
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Picks the executable for a launch: the profile's pinned browser, which
+/// must exist, or else the configured or first discovered one.
+pub(crate) fn resolve_browser(
+    pinned: Option<String>,
+    configured: Option<String>,
+) -> Result<PathBuf, ShadowError> {
+    let chrome_path = match pinned {
+        Some(pinned) => {
+            pinned_browser(&pinned).ok_or(ShadowError::ExecutableMissing { path: Some(pinned) })?
+        }
+        None => find_browser(configured.as_deref())
+            .ok_or(ShadowError::ExecutableMissing { path: None })?,
+    };
+    eprintln!("Using browser executable: {}", chrome_path.display());
+    Ok(chrome_path)
+}
+
+/// The local relay address in a `--proxy-server` value.
+pub(crate) fn relay_addr(proxy: &str) -> Option<SocketAddr> {
+    proxy.strip_prefix("socks5://")?.parse().ok()
+}
+
+/// Gives back a launch's lease on the relay behind `proxy`, which stops once
+/// no launch holds it anymore.
+pub(crate) async fn release_relay(proxy: Option<&str>) {
+    let Some(addr) = proxy.and_then(relay_addr) else {
+        return;
+    };
+    if RELAYS.release(addr).await {
+        eprintln!("Relay {} stopped.", addr);
+    }
+}
+
+/// Starts Chrome from `spec` and tracks it until it exits. Startup failures
+/// are only announced through `chrome-closed` when `report_failure` is set;
+/// restarts report their own outcome.
+pub(crate) async fn start_instance(
+    spec: LaunchSpec,
+    restarts: u32,
+    report_failure: bool,
+) -> Result<ChromeLaunchInfo, ShadowError> {
+    let os = if cfg!(target_os = "windows") {
+        "Windows".to_string()
+    } else if cfg!(target_os = "macos") {
+        "Mac".to_string()
+    } else if cfg!(target_os = "linux") {
+        "Linux".to_string()
+    } else {
+        return Err(ShadowError::UnsupportedOs);
+    };
+    let user_dir = spec.user_dir.clone();
+    // Held until Chrome is tracked or given up on, so a second launch of the
+    // profile cannot slip in before the instance is inserted.
+    let Some(_starting) = StartingDir::claim(&user_dir).await else {
+        return Err(ShadowError::ProfileLocked { user_dir });
+    };
+    // Without an explicit port Chrome picks a free one itself and reports it
+    // through DevToolsActivePort, so nothing can grab it in between.
+    let mut lease = match spec.port {
+        Some(port) => Some(
+            DEBUG_PORTS
+                .acquire(port)
+                .or_else(|| DEBUG_PORTS.allocate(port.saturating_add(1)))
+                .ok_or(ShadowError::PortExhausted)?,
+        ),
+        None => None,
+    };
+    let display = match spec.mode {
+        LaunchMode::VirtualDisplay => {
+            let pool = spec.display_pool.clone();
+            let lease = task::spawn_blocking(move || VIRTUAL_DISPLAYS.acquire(pool.as_deref()))
+                .await
+                .map_err(|e| ShadowError::Internal(e.to_string()))??;
+            Some(Arc::new(lease))
+        }
+        _ => None,
+    };
+    let mut attempt = 1;
+    loop {
+        let requested_port = lease.as_ref().map(|lease| lease.port());
+        let mut args = vec![
+            format!("--user-data-dir={}", user_dir),
+            format!("--remote-debugging-port={}", requested_port.unwrap_or(0)),
+            format!(
+                "--window-name={}",
+                Path::new(&user_dir)
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .unwrap()
+            ),
+            "--no-first-run".to_string(),
+            "--hide-crash-restore-bubble".to_string(),
+        ];
+        if let Some(proxy) = &spec.proxy {
+            args.push(format!("--proxy-server={}", proxy));
+        }
+        args.extend(spec.mode.args().iter().map(|arg| arg.to_string()));
+        args.extend(spec.extras.args.iter().cloned());
+        let mut cmd = Command::new(&spec.chrome_path);
+        cmd.args(&args).envs(&spec.extras.env);
+        if let Some(display) = &display {
+            cmd.env("DISPLAY", display.name());
+        }
+        clear_port_file(Path::new(&user_dir));
+        let process = match ChromeProcess::spawn(&mut cmd) {
+            Ok(process) => process,
+            Err(e) => return Err(ShadowError::SpawnFailed(e.to_string())),
+        };
+        let pid = process.pid();
+        let instance = ChromeInstance {
+            id: spec.id,
+            pid,
+            user_dir: user_dir.clone(),
+            port: requested_port.unwrap_or(0),
+            os: os.clone(),
+            proxy: spec
+                .proxy
+                .as_deref()
+                .and_then(relay_addr)
+                .map(|addr| addr.to_string()),
+            ws: None,
+            mode: spec.mode,
+            restarts,
+            process: process.clone(),
+            spec: Arc::new(spec.clone()),
+            lease: None,
+            display: display.as_ref().map(|display| display.name()),
+            display_lease: display.clone(),
+        };
+        CHROME_INSTANCES.lock().await.insert(pid, instance);
+        match wait_for_devtools(
+            &process,
+            Path::new(&user_dir),
+            requested_port,
+            spec.startup_timeout,
+        )
+        .await
+        {
+            Ok(DevToolsEndpoint { port, ws }) => {
+                let lease = lease.or_else(|| DEBUG_PORTS.adopt(port)).map(Arc::new);
+                if let Some(lease) = &lease {
+                    lease.assign(pid);
+                }
+                if let Some(instance) = CHROME_INSTANCES.lock().await.get_mut(&pid) {
+                    instance.port = port;
+                    instance.ws = Some(ws.clone());
+                    instance.lease = lease;
+                }
+                save_instances().await;
+                host().emit(
+                    "chrome-started",
+                    ChromeStarted {
+                        user_dir,
+                        pid,
+                        ws: ws.clone(),
+                    },
+                );
+                task::spawn(monitor_chrome(process, spec, port, restarts));
+                return Ok(ChromeLaunchInfo {
+                    pid,
+                    port,
+                    os,
+                    ws: Some(ws),
+                });
+            }
+            Err(e) => {
+                eprintln!("Wait for chrome start failed: {}", e);
+                CHROME_INSTANCES.lock().await.remove(&pid);
+                // Marked as closing so the exit watcher also sweeps any
+                // helpers before it reaps the browser.
+                process.mark_closing();
+                let exit = match process.kill_tree() {
+                    Ok(()) => process.wait().await,
+                    Err(_) => process.exit_info().unwrap_or_default(),
+                };
+                if let StartupError::PortInUse { port } = e {
+                    if attempt < PORT_BIND_ATTEMPTS {
+                        attempt += 1;
+                        lease = Some(
+                            DEBUG_PORTS
+                                .allocate(port.saturating_add(1))
+                                .ok_or(ShadowError::PortExhausted)?,
+                        );
+                        continue;
+                    }
+                }
+                if report_failure {
+                    host().emit(
+                        "chrome-closed",
+                        ChromeStoped {
+                            pid,
+                            proxy: spec.proxy.clone(),
+                            reason: CloseReason::StartupFailed,
+                            exit,
+                        },
+                    );
+                }
+                return Err(match e {
+                    // Chrome hands the launch over to the browser already
+                    // holding the profile and exits cleanly.
+                    StartupError::Crashed { code: Some(0) } if is_profile_locked(&user_dir) => {
+                        ShadowError::ProfileLocked { user_dir }
+                    }
+                    e => e.into(),
+                });
+            }
+        }
+    }
+}
+
+/// Chrome keeps a singleton lock in the user data dir while a browser uses it.
+fn is_profile_locked(user_dir: &str) -> bool {
+    ["SingletonLock", "lockfile"]
+        .iter()
+        .any(|name| Path::new(user_dir).join(name).symlink_metadata().is_ok())
+}
+
+pub async fn close_instance(
+    pid: u32,
+    options: ShutdownOptions,
+) -> Result<ChromeClosed, ShadowError> {
+    let instance = {
+        let mut instances = CHROME_INSTANCES.lock().await;
+        instances.remove(&pid)
+    };
+    save_instances().await;
+    match instance {
+        Some(instance) => {
+            eprintln!("Attempting to close Chrome with PID: {}", pid);
+            match shutdown_chrome(&instance.process, instance.ws.as_deref(), &options).await {
+                Ok(step) => Ok(ChromeClosed {
+                    pid,
+                    os: instance.os,
+                    step,
+                }),
+                Err(e) => Err(ShadowError::ShutdownFailed(e.to_string())),
+            }
+        }
+        None => Err(ShadowError::InstanceNotFound),
+    }
+}
+
+/// Closes every tracked instance, then the relays they went through.
+pub async fn close_all(options: ShutdownOptions) {
+    let pids: Vec<u32> = CHROME_INSTANCES.lock().await.keys().copied().collect();
+    join_all(pids.into_iter().map(|pid| {
+        let options = options.clone();
+        async move {
+            if let Err(e) = close_instance(pid, options).await {
+                eprintln!("Close Chrome (PID: {}) failed: {}", pid, e);
+            }
+        }
+    }))
+    .await;
+    RELAYS.stop_all().await;
+}
@@ -0,0 +1,66 @@
+//! Profile launching, the SOCKS relay and database access, without Tauri.
+//!
+//! The GUI, the daemon and the CLI are built on this. To embed it, set up a
+//! [`Host`] with [`init`], or use the app's own data with [`open_app_data`],
+//! then launch profiles with [`launch_profile`] and close them with
+//! [`close_instance`] or [`close_all`].
+
+pub mod args;
+pub mod browser;
+pub mod db;
+pub mod devtools;
+pub mod display;
+pub mod error;
+pub mod host;
+pub mod instances;
+pub mod ports;
+pub mod process;
+pub mod profile;
+pub mod proxy_manager;
+pub mod reattach;
+pub mod restart;
+pub mod shadowsocks;
+pub mod shutdown;
+pub mod store;
+
+pub use self::error::ShadowError;
+pub use self::host::{Database, EventSink, Host};
+pub use self::instances::{
+    close_all, close_instance, list_chrome_instances, ChromeInstance, ChromeLaunchInfo,
+};
+pub use self::profile::{launch_profile, LaunchOverrides};
+pub use self::proxy_manager::{Proxy, ProxyChain, ProxyManager, RELAYS};
+
+use self::host::IDENTIFIER;
+use self::reattach::reattach_instances;
+use std::path::PathBuf;
+
+/// Where the app's HTTP API listens; the CLI looks for a running app here.
+pub const API_ADDR: (&str, u16) = ("127.0.0.1", 51888);
+
+/// Makes `host` the one everything reports to and keeps instance state in
+/// its data dir. Call once, before launching anything.
+pub fn init(host: Host) {
+    let data_dir = host.data_dir().to_path_buf();
+    host::init(host);
+    reattach::init(&data_dir);
+}
+
+/// Where Tauri puts the app's data and config dirs for our identifier.
+fn app_dir(base: Option<PathBuf>) -> Result<PathBuf, ShadowError> {
+    base.map(|dir| dir.join(IDENTIFIER))
+        .ok_or(ShadowError::Internal(
+            "cannot determine the app directories".to_string(),
+        ))
+}
+
+/// Sets up the core on the GUI's database, settings and profiles, and takes
+/// over the instances the app or another process left running.
+pub async fn open_app_data(events: impl EventSink + 'static) -> Result<(), ShadowError> {
+    let data_dir = app_dir(dirs::data_dir())?;
+    let config_dir = app_dir(dirs::config_dir())?;
+    let pool = db::open(&config_dir).await?;
+    init(Host::new(events, pool, data_dir));
+    reattach_instances(host::host().profiles_dir()).await;
+    Ok(())
+}
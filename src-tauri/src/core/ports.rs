@@ -0,0 +1,106 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+lazy_static::lazy_static! {
+    pub static ref DEBUG_PORTS: PortAllocator = PortAllocator::default();
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortLeaseInfo {
+    pub port: u16,
+    pub pid: Option<u32>,
+    pub leased_at: u64,
+}
+
+/// Registry of the DevTools ports owned by our Chrome instances. A port is
+/// only handed out once and is returned as soon as its [`PortLease`] is
+/// dropped, whichever way the instance went away.
+#[derive(Debug, Default)]
+pub struct PortAllocator {
+    leases: Mutex<BTreeMap<u16, PortLeaseInfo>>,
+}
+
+#[derive(Debug)]
+pub struct PortLease {
+    port: u16,
+    allocator: &'static PortAllocator,
+}
+
+impl PortAllocator {
+    fn insert(&'static self, port: u16, probe: bool) -> Option<PortLease> {
+        let mut leases = self.leases.lock().unwrap();
+        if leases.contains_key(&port) {
+            return None;
+        }
+        // The probe runs under the lock so two launches cannot both see the
+        // port as free.
+        if probe && TcpListener::bind(("127.0.0.1", port)).is_err() {
+            return None;
+        }
+        let leased_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        leases.insert(
+            port,
+            PortLeaseInfo {
+                port,
+                pid: None,
+                leased_at,
+            },
+        );
+        Some(PortLease {
+            port,
+            allocator: self,
+        })
+    }
+
+    /// Leases a specific port that Chrome has not bound yet. Fails when the
+    /// port is already leased or something else on the machine holds it.
+    pub fn acquire(&'static self, port: u16) -> Option<PortLease> {
+        self.insert(port, true)
+    }
+
+    /// Leases the first free port from `start` upwards.
+    pub fn allocate(&'static self, start: u16) -> Option<PortLease> {
+        let lease = (start..u16::MAX).find_map(|port| self.acquire(port))?;
+        eprintln!("Port: {} leased.", lease.port);
+        Some(lease)
+    }
+
+    /// Records a port Chrome already bound by itself (`--remote-debugging-port=0`).
+    pub fn adopt(&'static self, port: u16) -> Option<PortLease> {
+        self.insert(port, false)
+    }
+
+    pub fn leases(&self) -> Vec<PortLeaseInfo> {
+        self.leases.lock().unwrap().values().cloned().collect()
+    }
+
+    fn release(&self, port: u16) {
+        if self.leases.lock().unwrap().remove(&port).is_some() {
+            eprintln!("Port: {} released.", port);
+        }
+    }
+}
+
+impl PortLease {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn assign(&self, pid: u32) {
+        if let Some(info) = self.allocator.leases.lock().unwrap().get_mut(&self.port) {
+            info.pid = Some(pid);
+        }
+    }
+}
+
+impl Drop for PortLease {
+    fn drop(&mut self) {
+        self.allocator.release(self.port);
+    }
+}
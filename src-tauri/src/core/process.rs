@@ -0,0 +1,642 @@
+use serde::Serialize;
+use std::io::Result;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::sync::watch;
+
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+/// How a Chrome process ended: the exit code, or on unix the signal that
+/// killed it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ExitInfo {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+impl From<ExitStatus> for ExitInfo {
+    fn from(status: ExitStatus) -> Self {
+        ExitInfo {
+            code: status.code(),
+            #[cfg(unix)]
+            signal: status.signal(),
+            #[cfg(not(unix))]
+            signal: None,
+        }
+    }
+}
+
+/// A Chrome process together with everything needed to tear down the whole
+/// tree it creates (renderers, GPU process, crashpad handler...). It is
+/// either spawned by us or adopted from a previous run of the app.
+#[derive(Debug)]
+pub struct ChromeProcess {
+    pid: u32,
+    /// `None` for adopted processes, which are not our children. Only the
+    /// exit watcher reaps the child, and only while holding this lock, so
+    /// whoever signals under it knows the pid is still ours.
+    child: Option<Mutex<Child>>,
+    /// When an adopted process started, to tell it from a later process
+    /// that got the same pid.
+    #[cfg(target_os = "linux")]
+    start_time: Option<u64>,
+    closing: AtomicBool,
+    exit: watch::Receiver<Option<ExitInfo>>,
+    #[cfg(windows)]
+    job: Option<windows::Job>,
+    #[cfg(windows)]
+    handle: Option<windows::Process>,
+}
+
+impl ChromeProcess {
+    /// Spawns Chrome along with a small thread blocked on its exit, which
+    /// keeps the process alive until then.
+    pub fn spawn(cmd: &mut Command) -> Result<Arc<ChromeProcess>> {
+        // Chrome and all of its helpers share one process group, so the
+        // group can be signalled as a unit.
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let child = cmd.spawn()?;
+        let pid = child.id();
+        #[cfg(windows)]
+        let job = windows::Job::for_child(&child)
+            .map_err(|e| eprintln!("Failed to create job object for pid {}: {}", pid, e))
+            .ok();
+        let (exit_tx, exit) = watch::channel(None);
+        let process = Arc::new(ChromeProcess {
+            pid,
+            child: Some(Mutex::new(child)),
+            #[cfg(target_os = "linux")]
+            start_time: None,
+            closing: AtomicBool::new(false),
+            exit,
+            #[cfg(windows)]
+            job,
+            #[cfg(windows)]
+            handle: None,
+        });
+        if let Err(e) = Self::watch_exit(&process, exit_tx) {
+            process.kill_tree()?;
+            return Err(e);
+        }
+        Ok(process)
+    }
+
+    /// Takes over a Chrome started by an earlier run of the app. Its exit
+    /// code cannot be collected, so it is reported as unknown.
+    pub fn adopt(pid: u32) -> Result<Arc<ChromeProcess>> {
+        let not_running = || {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Process {} is not running", pid),
+            )
+        };
+        if !is_pid_alive(pid) {
+            return Err(not_running());
+        }
+        #[cfg(target_os = "linux")]
+        let start_time = Some(linux::start_time(pid).ok_or_else(not_running)?);
+        let (exit_tx, exit) = watch::channel(None);
+        let process = Arc::new(ChromeProcess {
+            pid,
+            child: None,
+            #[cfg(target_os = "linux")]
+            start_time,
+            closing: AtomicBool::new(false),
+            exit,
+            #[cfg(windows)]
+            job: None,
+            #[cfg(windows)]
+            handle: Some(windows::Process::open(pid)?),
+        });
+        Self::watch_exit(&process, exit_tx)?;
+        Ok(process)
+    }
+
+    fn watch_exit(
+        process: &Arc<ChromeProcess>,
+        exit_tx: watch::Sender<Option<ExitInfo>>,
+    ) -> Result<()> {
+        let watched = process.clone();
+        thread::Builder::new()
+            .name(format!("chrome-exit-{}", process.pid))
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                watched.block_until_exit(&exit_tx);
+            })?;
+        Ok(())
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Marks the process as being shut down by us, so its exit is not
+    /// mistaken for a crash.
+    pub fn mark_closing(&self) {
+        self.closing.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the process has exited, without polling.
+    pub async fn wait(&self) -> ExitInfo {
+        let mut exit = self.exit.clone();
+        let exit = exit
+            .wait_for(|exit| exit.is_some())
+            .await
+            .map(|exit| exit.unwrap_or_default());
+        exit.unwrap_or_else(|_| self.exit_info().unwrap_or_default())
+    }
+
+    // Waits for the exit without holding the child lock, so `kill_tree` and
+    // friends keep working meanwhile, then reaps it. When we are closing it,
+    // helpers left in its process group are killed first: until it is
+    // reaped, its pid and process group cannot be reused.
+    fn block_until_exit(&self, exit_tx: &watch::Sender<Option<ExitInfo>>) {
+        let Some(child) = &self.child else {
+            #[cfg(unix)]
+            unix::wait_foreign_exit(self.pid);
+            #[cfg(windows)]
+            if let Some(handle) = &self.handle {
+                handle.wait();
+            }
+            exit_tx.send_replace(Some(ExitInfo::default()));
+            return;
+        };
+        #[cfg(unix)]
+        unix::wait_exit(self.pid);
+        #[cfg(windows)]
+        {
+            use std::os::windows::io::AsRawHandle;
+            let handle = child.lock().unwrap().as_raw_handle();
+            windows::wait_exit(handle);
+        }
+        let mut child = child.lock().unwrap();
+        #[cfg(unix)]
+        if self.is_closing() {
+            let _ = unix::kill_tree(self.pid);
+        }
+        let exit = match child.try_wait() {
+            Ok(Some(status)) => status.into(),
+            _ => child.wait().map(ExitInfo::from).unwrap_or_default(),
+        };
+        // Published before the lock is released, so a signaller that gets
+        // the lock next sees the pid is no longer ours.
+        exit_tx.send_replace(Some(exit));
+    }
+
+    /// How the process exited, once it has.
+    pub fn exit_info(&self) -> Option<ExitInfo> {
+        *self.exit.borrow()
+    }
+
+    /// Checks liveness without shelling out.
+    pub fn is_running(&self) -> bool {
+        self.exit_info().is_none() && self.is_same_process()
+    }
+
+    /// Whether the pid still belongs to this process. A child cannot be
+    /// reused before it is reaped; an adopted pid is checked against the
+    /// start time it had when adopted.
+    fn is_same_process(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.child.is_none() {
+            return self.start_time.is_some()
+                && linux::start_time(self.pid) == self.start_time
+                && is_pid_alive(self.pid);
+        }
+        is_pid_alive(self.pid)
+    }
+
+    /// Runs `signal` unless the process is gone, holding the child lock so
+    /// it cannot be reaped meanwhile. Returns whether it ran.
+    fn signal_if_ours(
+        &self,
+        signal: impl FnOnce(Option<&mut Child>) -> Result<()>,
+    ) -> Result<bool> {
+        let mut child = self.child.as_ref().map(|child| child.lock().unwrap());
+        if self.exit.borrow().is_some() {
+            return Ok(false);
+        }
+        // An exited child stays a zombie until reaped, so its group can
+        // still be signalled; an adopted pid may already be someone else's.
+        if self.child.is_none() && !self.is_same_process() {
+            return Ok(false);
+        }
+        signal(child.as_deref_mut())?;
+        Ok(true)
+    }
+
+    /// Asks the process group to exit with SIGTERM. Returns `false` where
+    /// there is no such signal (Windows), so callers go straight to a kill.
+    pub fn terminate(&self) -> Result<bool> {
+        #[cfg(unix)]
+        {
+            self.signal_if_ours(|_| unix::signal(-(self.pid as i32), libc::SIGTERM))?;
+            Ok(true)
+        }
+        #[cfg(not(unix))]
+        Ok(false)
+    }
+
+    /// Kills Chrome and every process it spawned, unless it has already
+    /// exited. The exit watcher reaps the main process.
+    pub fn kill_tree(&self) -> Result<()> {
+        self.signal_if_ours(|child| {
+            #[cfg(unix)]
+            {
+                let _ = child;
+                unix::kill_tree(self.pid)?;
+            }
+            #[cfg(windows)]
+            if let Some(job) = &self.job {
+                job.terminate()?;
+            }
+            // Without a job object only the browser process can be killed;
+            // its helpers exit on their own once it is gone.
+            #[cfg(windows)]
+            if let Some(handle) = &self.handle {
+                handle.terminate()?;
+            }
+            #[cfg(windows)]
+            if let Some(child) = child.filter(|_| self.job.is_none()) {
+                child.kill()?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+/// Whether a process with the given pid exists and has not exited.
+#[cfg(target_os = "linux")]
+pub fn is_pid_alive(pid: u32) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => !matches!(linux::stat_state(&stat), Some('Z') | Some('X') | None),
+        Err(_) => false,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn is_pid_alive(pid: u32) -> bool {
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+pub fn is_pid_alive(pid: u32) -> bool {
+    windows::is_pid_alive(pid)
+}
+
+/// The arguments a process was started with, if it is still running.
+#[cfg(target_os = "linux")]
+pub fn command_line(pid: u32) -> Option<Vec<String>> {
+    linux::command_line(pid)
+}
+
+/// A running Chrome browser process (not one of its helpers) and the
+/// profile directory it was started with.
+#[derive(Debug, Clone)]
+pub struct BrowserProcess {
+    pub pid: u32,
+    pub user_dir: String,
+}
+
+const USER_DATA_DIR_ARG: &str = "--user-data-dir=";
+
+/// Every Chromium-family browser process currently running with an explicit
+/// `--user-data-dir`.
+#[cfg(target_os = "linux")]
+pub fn browser_processes() -> Vec<BrowserProcess> {
+    linux::browser_processes()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn browser_processes() -> Vec<BrowserProcess> {
+    #[cfg(windows)]
+    let output = {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-CimInstance Win32_Process | ForEach-Object { \"$($_.ProcessId) $($_.CommandLine)\" }",
+            ])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+    };
+    #[cfg(not(windows))]
+    let output = Command::new("ps")
+        .args(["-axww", "-o", "pid=,args="])
+        .output();
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, command_line) = line.trim().split_once(' ')?;
+            if command_line.contains(" --type=") {
+                return None;
+            }
+            Some(BrowserProcess {
+                pid: pid.parse().ok()?,
+                user_dir: user_dir_from_command_line(command_line)?,
+            })
+        })
+        .collect()
+}
+
+// The command line only comes back as one string here, so the value runs up
+// to the next switch, minus the quoting added around paths with spaces.
+#[cfg(not(target_os = "linux"))]
+fn user_dir_from_command_line(command_line: &str) -> Option<String> {
+    let start = command_line.find(USER_DATA_DIR_ARG)? + USER_DATA_DIR_ARG.len();
+    let rest = &command_line[start..];
+    let end = rest.find(" --").unwrap_or(rest.len());
+    let value = rest[..end].trim().trim_matches('"');
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::HashMap;
+    use std::fs;
+
+    // The command name in /proc/<pid>/stat is wrapped in parentheses and may
+    // itself contain spaces or parentheses, so fields are read after the last
+    // closing one.
+    fn fields_after_comm(stat: &str) -> Option<Vec<&str>> {
+        let end = stat.rfind(')')?;
+        Some(stat[end + 1..].split_whitespace().collect())
+    }
+
+    pub fn stat_state(stat: &str) -> Option<char> {
+        fields_after_comm(stat)?.first()?.chars().next()
+    }
+
+    fn stat_ppid(stat: &str) -> Option<u32> {
+        fields_after_comm(stat)?.get(1)?.parse().ok()
+    }
+
+    /// When `pid` started, in clock ticks since boot; field 22 of its stat.
+    pub fn start_time(pid: u32) -> Option<u64> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        fields_after_comm(&stat)?.get(19)?.parse().ok()
+    }
+
+    /// The arguments `pid` was started with.
+    pub fn command_line(pid: u32) -> Option<Vec<String>> {
+        let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        Some(
+            cmdline
+                .split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect(),
+        )
+    }
+
+    pub fn browser_processes() -> Vec<super::BrowserProcess> {
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_str()?.parse().ok()?;
+                let cmdline = fs::read(entry.path().join("cmdline")).ok()?;
+                let args: Vec<String> = cmdline
+                    .split(|b| *b == 0)
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect();
+                if args.iter().any(|arg| arg.starts_with("--type=")) {
+                    return None;
+                }
+                let user_dir = args
+                    .iter()
+                    .find_map(|arg| arg.strip_prefix(super::USER_DATA_DIR_ARG))?;
+                Some(super::BrowserProcess {
+                    pid,
+                    user_dir: user_dir.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// All descendants of `root`, found by walking the parent links in /proc.
+    pub fn descendants(root: u32) -> Vec<u32> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        if let Ok(entries) = fs::read_dir("/proc") {
+            for entry in entries.flatten() {
+                let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                    continue;
+                };
+                let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+                    continue;
+                };
+                if let Some(ppid) = stat_ppid(&stat) {
+                    children.entry(ppid).or_default().push(pid);
+                }
+            }
+        }
+        let mut result = Vec::new();
+        let mut stack = vec![root];
+        while let Some(pid) = stack.pop() {
+            if let Some(kids) = children.get(&pid) {
+                result.extend(kids);
+                stack.extend(kids);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{Error, Result};
+
+    pub fn signal(pid: i32, sig: libc::c_int) -> Result<()> {
+        if unsafe { libc::kill(pid, sig) } == 0 {
+            return Ok(());
+        }
+        let err = Error::last_os_error();
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Blocks until `pid` has exited but leaves it to be reaped by `Child`.
+    pub fn wait_exit(pid: u32) {
+        loop {
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            let ret = unsafe {
+                libc::waitid(
+                    libc::P_PID,
+                    pid as libc::id_t,
+                    &mut info,
+                    libc::WEXITED | libc::WNOWAIT,
+                )
+            };
+            if ret == 0 || Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                return;
+            }
+        }
+    }
+
+    /// Blocks until a process that is not our child has exited.
+    pub fn wait_foreign_exit(pid: u32) {
+        #[cfg(target_os = "linux")]
+        {
+            let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+            if fd >= 0 {
+                let mut pollfd = libc::pollfd {
+                    fd: fd as libc::c_int,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                while unsafe { libc::poll(&mut pollfd, 1, -1) } < 0
+                    && Error::last_os_error().raw_os_error() == Some(libc::EINTR)
+                {}
+                unsafe { libc::close(fd as libc::c_int) };
+                return;
+            }
+        }
+        // No pidfd (older kernels, other unixes): fall back to polling.
+        while super::is_pid_alive(pid) {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        }
+    }
+
+    pub fn kill_tree(pid: u32) -> Result<()> {
+        // Collect stragglers that left the process group before the group
+        // leader goes away and they get reparented.
+        #[cfg(target_os = "linux")]
+        let descendants = super::linux::descendants(pid);
+        signal(-(pid as i32), libc::SIGKILL)?;
+        #[cfg(target_os = "linux")]
+        for child in descendants {
+            signal(child as i32, libc::SIGKILL)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io::{Error, Result};
+    use std::os::windows::io::{AsRawHandle, RawHandle};
+    use std::process::Child;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, STILL_ACTIVE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, TerminateProcess, WaitForSingleObject, INFINITE,
+        PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SYNCHRONIZE, PROCESS_TERMINATE,
+    };
+
+    /// Job object holding Chrome and every process it starts, so the tree
+    /// can be terminated in one call. The job is not kill-on-close: browsers
+    /// outlive the app unless they are closed explicitly.
+    #[derive(Debug)]
+    pub struct Job(HANDLE);
+
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        pub fn for_child(child: &Child) -> Result<Job> {
+            let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if handle.is_null() {
+                return Err(Error::last_os_error());
+            }
+            let job = Job(handle);
+            if unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) } == 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        pub fn terminate(&self) -> Result<()> {
+            if unsafe { TerminateJobObject(self.0, 1) } == 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// Handle to a process we did not spawn, kept open so the pid cannot be
+    /// reused while we wait on it.
+    #[derive(Debug)]
+    pub struct Process(HANDLE);
+
+    unsafe impl Send for Process {}
+    unsafe impl Sync for Process {}
+
+    impl Process {
+        pub fn open(pid: u32) -> Result<Process> {
+            let access =
+                PROCESS_SYNCHRONIZE | PROCESS_TERMINATE | PROCESS_QUERY_LIMITED_INFORMATION;
+            let handle = unsafe { OpenProcess(access, 0, pid) };
+            if handle.is_null() {
+                return Err(Error::last_os_error());
+            }
+            Ok(Process(handle))
+        }
+
+        pub fn wait(&self) {
+            unsafe { WaitForSingleObject(self.0, INFINITE) };
+        }
+
+        pub fn terminate(&self) -> Result<()> {
+            if unsafe { TerminateProcess(self.0, 1) } == 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Process {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// Blocks until the process behind `handle` has exited.
+    pub fn wait_exit(handle: RawHandle) {
+        unsafe { WaitForSingleObject(handle as HANDLE, INFINITE) };
+    }
+
+    pub fn is_pid_alive(pid: u32) -> bool {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return false;
+            }
+            let mut code = 0u32;
+            let ok = GetExitCodeProcess(handle, &mut code);
+            CloseHandle(handle);
+            ok != 0 && code == STILL_ACTIVE as u32
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::core::args::{LaunchExtras, LaunchMode};
+use crate::core::devtools::DEFAULT_STARTUP_TIMEOUT_MS;
+use crate::core::error::ShadowError;
+use crate::core::host::host;
+use crate::core::instances::{
+    close_instance, release_relay, resolve_browser, start_instance, wait_for_profile,
+    ChromeLaunchInfo, LaunchSpec, CHROME_INSTANCES,
+};
+use crate::core::proxy_manager::{ProxyChain, RELAYS};
+use crate::core::restart::RestartPolicy;
+use crate::core::shutdown::{ShutdownOptions, ShutdownStep};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Pool, Row, Sqlite};
+use std::fs;
+use std::time::Duration;
+
+/// Settings for a single launch that take precedence over the profile's.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchOverrides {
+    pub port: Option<u16>,
+    pub mode: Option<LaunchMode>,
+    /// Virtual display instances naming the same pool share one Xvfb server.
+    pub display_pool: Option<String>,
+    pub startup_timeout_ms: Option<u64>,
+    #[serde(flatten)]
+    pub extras: LaunchExtras,
+}
+
+/// A `profile_table` row with what a launch needs.
+pub struct ProfileRow {
+    pub id: u16,
+    pub name: String,
+    pub proxy: Option<String>,
+    pub browser_path: Option<String>,
+    pub restart: RestartPolicy,
+    pub mode: LaunchMode,
+    pub extras: LaunchExtras,
+}
+
+/// Reads an enum stored as its snake_case name, falling back to the default
+/// for values written by a newer version.
+fn column_enum<T: DeserializeOwned + Default>(value: String) -> T {
+    serde_json::from_value(serde_json::Value::String(value)).unwrap_or_default()
+}
+
+pub async fn load_profile(pool: &Pool<Sqlite>, id: u16) -> Result<ProfileRow, ShadowError> {
+    let sql = format!("SELECT p.id, p.name, p.browser_path, p.restart_policy, p.restart_max_retries, p.restart_backoff_ms, p.launch_mode, p.extra_args, p.extra_env, pr.name AS proxy_name FROM profile_table p LEFT JOIN proxy_table pr ON p.proxy_id = pr.id WHERE p.id = {}", id);
+    eprintln!("{}", sql);
+    let query = sqlx::query(&sql);
+    let row = pool
+        .fetch_optional(query)
+        .await?
+        .ok_or(ShadowError::ProfileNotFound { id })?;
+    let max_retries: i64 = row.try_get("restart_max_retries")?;
+    let backoff_ms: i64 = row.try_get("restart_backoff_ms")?;
+    Ok(ProfileRow {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        proxy: row.try_get("proxy_name")?,
+        browser_path: row.try_get("browser_path")?,
+        restart: RestartPolicy {
+            mode: column_enum(row.try_get("restart_policy")?),
+            max_retries: max_retries.max(0) as u32,
+            backoff_ms: backoff_ms.max(0) as u64,
+        },
+        mode: column_enum(row.try_get("launch_mode")?),
+        extras: LaunchExtras::from_columns(row.try_get("extra_args")?, row.try_get("extra_env")?)?,
+    })
+}
+
+/// Launches profile `id` as stored in the database: starts or reuses a relay
+/// for its proxy, which is stopped again once no instance uses it.
+pub async fn launch_profile(
+    id: u16,
+    overrides: LaunchOverrides,
+) -> Result<ChromeLaunchInfo, ShadowError> {
+    let pool = host().db().await?;
+    let profile = load_profile(&pool, id).await?;
+    let extras = profile.extras.merge(overrides.extras);
+    extras.validate()?;
+    let chrome_path = resolve_browser(profile.browser_path, host().configured_browser())?;
+    let user_dir = host().profiles_dir().join(&profile.name);
+    fs::create_dir_all(&user_dir).map_err(|e| ShadowError::Internal(e.to_string()))?;
+    let (proxy, relay) = match profile.proxy {
+        Some(url) => {
+            let upstream = ProxyChain::from_url(&url).map_err(ShadowError::InvalidProxy)?;
+            let addr = RELAYS
+                .relay_for(upstream.clone())
+                .await
+                .map_err(|e| ShadowError::ProxyRelayFailed(e.to_string()))?;
+            (Some(format!("socks5://{}", addr)), Some(upstream))
+        }
+        None => (None, None),
+    };
+    let spec = LaunchSpec {
+        id: profile.id,
+        user_dir: user_dir.to_string_lossy().to_string(),
+        port: overrides.port,
+        proxy: proxy.clone(),
+        relay,
+        chrome_path,
+        mode: overrides.mode.unwrap_or(profile.mode),
+        display_pool: overrides.display_pool,
+        extras,
+        startup_timeout: Duration::from_millis(
+            overrides
+                .startup_timeout_ms
+                .unwrap_or(DEFAULT_STARTUP_TIMEOUT_MS),
+        ),
+        restart: profile.restart,
+    };
+    let result = start_instance(spec, 0, true).await;
+    if result.is_err() {
+        release_relay(proxy.as_deref()).await;
+    }
+    result
+}
+
+#[derive(Serialize, Clone)]
+struct LaunchProfileInfo {
+    id: u16,
+    name: String,
+}
+
+#[derive(Serialize, Clone)]
+struct CloseProfileInfo {
+    name: String,
+    pid: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OpenInfo {
+    pub id: u16,
+    /// Shorthand for `"mode": "headless"`.
+    pub headless: Option<bool>,
+    /// Launch mode, extra flags and environment for this launch only.
+    #[serde(flatten)]
+    pub overrides: LaunchOverrides,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChromeOpenInfo {
+    pub ws: String,
+    pub port: u16,
+    pub pid: u32,
+}
+
+impl From<ChromeLaunchInfo> for ChromeOpenInfo {
+    fn from(info: ChromeLaunchInfo) -> Self {
+        ChromeOpenInfo {
+            ws: info.ws.unwrap_or_default(),
+            port: info.port,
+            pid: info.pid,
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct LaunchFailedInfo {
+    id: u16,
+    name: String,
+    error: ShadowError,
+}
+
+/// Launches the profile in-process and answers once its DevTools endpoint is
+/// up. The GUI is only notified, so this works without the webview.
+pub async fn open_profile(info: OpenInfo) -> Result<ChromeOpenInfo, ShadowError> {
+    let pool = host().db().await?;
+    let profile = load_profile(&pool, info.id).await?;
+    let mut overrides = info.overrides;
+    if overrides.mode.is_none() {
+        overrides.mode = info.headless.map(|headless| match headless {
+            true => LaunchMode::Headless,
+            false => LaunchMode::Headed,
+        });
+    }
+    let timeout_ms = overrides
+        .startup_timeout_ms
+        .unwrap_or(DEFAULT_STARTUP_TIMEOUT_MS);
+    // Already running, or being started by someone else.
+    if let Some(running) = wait_for_profile(profile.id, Duration::from_millis(timeout_ms)).await? {
+        return Ok(running.into());
+    }
+    host().emit(
+        "chrome-api-launch",
+        LaunchProfileInfo {
+            id: profile.id,
+            name: profile.name.clone(),
+        },
+    );
+    match launch_profile(profile.id, overrides).await {
+        Ok(launched) => Ok(launched.into()),
+        Err(e) => {
+            host().emit(
+                "chrome-api-launch-failed",
+                LaunchFailedInfo {
+                    id: profile.id,
+                    name: profile.name,
+                    error: e.clone(),
+                },
+            );
+            Err(e)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CloseInfo {
+    pub id: u16,
+    #[serde(flatten)]
+    pub options: ShutdownOptions,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ClosedProfileInfo {
+    pub name: String,
+    pub pid: u32,
+    pub step: ShutdownStep,
+}
+
+pub async fn close_profile(info: CloseInfo) -> Result<ClosedProfileInfo, ShadowError> {
+    let pool = host().db().await?;
+    let sql = format!("SELECT id, name FROM profile_table WHERE id = {}", info.id);
+    eprintln!("{}", sql);
+    let query = sqlx::query(&sql);
+    let row = pool
+        .fetch_optional(query)
+        .await?
+        .ok_or(ShadowError::ProfileNotFound { id: info.id })?;
+    let id: u16 = row.try_get("id")?;
+    let name: String = row.try_get("name")?;
+    // Instances still starting count too, so a launch can be called off.
+    let pid = CHROME_INSTANCES
+        .lock()
+        .await
+        .values()
+        .find(|chrome| chrome.id == id)
+        .map(|chrome| chrome.pid)
+        .ok_or(ShadowError::InstanceNotFound)?;
+    host().emit(
+        "chrome-api-close",
+        CloseProfileInfo {
+            name: name.clone(),
+            pid,
+        },
+    );
+    let closed = close_instance(pid, info.options).await?;
+    Ok(ClosedProfileInfo {
+        name,
+        pid: closed.pid,
+        step: closed.step,
+    })
+}
@@ -0,0 +1,231 @@
+use crate::core::args::{LaunchExtras, LaunchMode};
+use crate::core::devtools::browser_ws;
+use crate::core::display::VIRTUAL_DISPLAYS;
+use crate::core::host::host;
+use crate::core::instances::{
+    monitor_chrome, relay_addr, ChromeInstance, LaunchSpec, CHROME_INSTANCES,
+};
+use crate::core::ports::DEBUG_PORTS;
+use crate::core::process::{browser_processes, BrowserProcess, ChromeProcess};
+use crate::core::proxy_manager::{ProxyChain, RELAYS};
+use crate::core::restart::RestartPolicy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::task;
+
+const INSTANCES_FILE: &str = "instances.json";
+
+static STATE_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// What is kept on disk about a running instance, enough to take it over
+/// again after the app restarts.
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceRecord {
+    id: u16,
+    pid: u32,
+    user_dir: String,
+    port: u16,
+    os: String,
+    proxy: Option<String>,
+    relay: Option<ProxyChain>,
+    chrome_path: PathBuf,
+    #[serde(default)]
+    mode: LaunchMode,
+    /// Number and Xvfb pid of the virtual display the instance draws into.
+    #[serde(default)]
+    display: Option<(u32, u32)>,
+    #[serde(default)]
+    display_pool: Option<String>,
+    #[serde(default)]
+    extras: LaunchExtras,
+    startup_timeout_ms: u64,
+    restart: RestartPolicy,
+    restarts: u32,
+}
+
+impl InstanceRecord {
+    fn new(instance: &ChromeInstance) -> Self {
+        let spec = &instance.spec;
+        InstanceRecord {
+            id: instance.id,
+            pid: instance.pid,
+            user_dir: instance.user_dir.clone(),
+            port: instance.port,
+            os: instance.os.clone(),
+            proxy: spec.proxy.clone(),
+            relay: spec.relay.clone(),
+            chrome_path: spec.chrome_path.clone(),
+            mode: spec.mode,
+            display: instance
+                .display_lease
+                .as_ref()
+                .and_then(|d| Some((d.number(), d.xvfb_pid()?))),
+            display_pool: spec.display_pool.clone(),
+            extras: spec.extras.clone(),
+            startup_timeout_ms: spec.startup_timeout.as_millis() as u64,
+            restart: spec.restart.clone(),
+            restarts: instance.restarts,
+        }
+    }
+
+    fn spec(&self) -> LaunchSpec {
+        LaunchSpec {
+            id: self.id,
+            user_dir: self.user_dir.clone(),
+            port: Some(self.port),
+            proxy: self.proxy.clone(),
+            relay: self.relay.clone(),
+            chrome_path: self.chrome_path.clone(),
+            mode: self.mode,
+            display_pool: self.display_pool.clone(),
+            extras: self.extras.clone(),
+            startup_timeout: Duration::from_millis(self.startup_timeout_ms),
+            restart: self.restart.clone(),
+        }
+    }
+}
+
+/// Sets where instance state is kept; nothing is saved before this.
+pub fn init(app_data_dir: &Path) {
+    let _ = STATE_FILE.set(app_data_dir.join(INSTANCES_FILE));
+}
+
+/// Writes every started instance to disk. Called whenever the table changes.
+pub async fn save_instances() {
+    let Some(path) = STATE_FILE.get() else {
+        return;
+    };
+    // Written under the table lock so concurrent saves land in order.
+    let instances = CHROME_INSTANCES.lock().await;
+    let records: Vec<InstanceRecord> = instances
+        .values()
+        .filter(|inst| inst.ws.is_some())
+        .map(InstanceRecord::new)
+        .collect();
+    let tmp = path.with_extension("json.tmp");
+    let result = serde_json::to_vec_pretty(&records)
+        .map_err(std::io::Error::from)
+        .and_then(|json| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&tmp, json)?;
+            fs::rename(&tmp, path)
+        });
+    if let Err(e) = result {
+        eprintln!("Save instances to {} failed: {}", path.display(), e);
+    }
+}
+
+fn load_records() -> Vec<InstanceRecord> {
+    let Some(path) = STATE_FILE.get() else {
+        return Vec::new();
+    };
+    match fs::read(path) {
+        Ok(json) => serde_json::from_slice(&json).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Takes over the browsers a previous run of the app left open: every
+/// recorded instance whose profile under `profiles_root` is still open in a
+/// live browser, and whose DevTools port still answers, is tracked and
+/// monitored again.
+pub async fn reattach_instances(profiles_root: PathBuf) {
+    let records = load_records();
+    if records.is_empty() {
+        return;
+    }
+    let running = task::spawn_blocking(browser_processes)
+        .await
+        .unwrap_or_default();
+    let mut reattached = 0;
+    for record in records {
+        if !Path::new(&record.user_dir).starts_with(&profiles_root) {
+            continue;
+        }
+        let Some(process) = running.iter().find(|p| p.user_dir == record.user_dir) else {
+            eprintln!("Chrome for {} is no longer running.", record.user_dir);
+            continue;
+        };
+        match reattach(record, process).await {
+            Ok(()) => reattached += 1,
+            Err(e) => eprintln!("Reattach Chrome (PID: {}) failed: {}", process.pid, e),
+        }
+    }
+    save_instances().await;
+    if reattached > 0 {
+        host().emit("profile-refresh", ());
+    }
+}
+
+async fn reattach(record: InstanceRecord, running: &BrowserProcess) -> Result<(), String> {
+    let ws = browser_ws(record.port)
+        .await
+        .ok_or(format!("DevTools port {} does not answer", record.port))?;
+    let relay = match (&record.proxy, &record.relay) {
+        (Some(proxy), Some(relay)) => {
+            let addr = relay_addr(proxy).ok_or(format!("Invalid relay address {}", proxy))?;
+            RELAYS
+                .restore_server(addr, relay.clone())
+                .await
+                .map_err(|e| format!("Restore relay {} failed: {}", addr, e))?;
+            Some(addr)
+        }
+        _ => None,
+    };
+    let result = adopt(record, running, ws).await;
+    if let (Err(_), Some(addr)) = (&result, relay) {
+        RELAYS.release(addr).await;
+    }
+    result
+}
+
+/// Tracks and monitors the browser of `record` again, its relay restored.
+async fn adopt(record: InstanceRecord, running: &BrowserProcess, ws: String) -> Result<(), String> {
+    let display_lease = match record.display {
+        Some((number, xvfb_pid)) => Some(Arc::new(
+            VIRTUAL_DISPLAYS
+                .adopt(number, record.display_pool.as_deref(), xvfb_pid)
+                .map_err(|e| e.to_string())?,
+        )),
+        None => None,
+    };
+    let process = ChromeProcess::adopt(running.pid).map_err(|e| e.to_string())?;
+    let pid = process.pid();
+    let lease = DEBUG_PORTS.adopt(record.port).map(Arc::new);
+    if let Some(lease) = &lease {
+        lease.assign(pid);
+    }
+    let spec = record.spec();
+    let instance = ChromeInstance {
+        id: record.id,
+        pid,
+        user_dir: record.user_dir.clone(),
+        port: record.port,
+        os: record.os.clone(),
+        proxy: record
+            .proxy
+            .as_deref()
+            .and_then(relay_addr)
+            .map(|addr| addr.to_string()),
+        ws: Some(ws),
+        mode: record.mode,
+        restarts: record.restarts,
+        process: process.clone(),
+        spec: Arc::new(spec.clone()),
+        lease,
+        display: display_lease.as_ref().map(|d| d.name()),
+        display_lease,
+    };
+    CHROME_INSTANCES.lock().await.insert(pid, instance);
+    eprintln!("Reattached Chrome (PID: {}) for {}", pid, record.user_dir);
+    task::spawn(monitor_chrome(process, spec, record.port, record.restarts));
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use crate::core::instances::CloseReason;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Backoff never grows past this, however many restarts in a row failed.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// An instance that stayed up this long is considered healthy again, so its
+/// next crash starts counting retries from zero.
+pub const STABLE_RUN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartMode {
+    #[default]
+    Never,
+    OnCrash,
+    Always,
+}
+
+/// Per-profile policy for bringing Chrome back after it exits on its own.
+/// Instances closed by the app are never restarted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    pub mode: RestartMode,
+    pub max_retries: u32,
+    pub backoff_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            mode: RestartMode::Never,
+            max_retries: 3,
+            backoff_ms: 1000,
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub(crate) fn should_restart(&self, reason: CloseReason) -> bool {
+        match self.mode {
+            RestartMode::Never => false,
+            RestartMode::OnCrash => reason == CloseReason::Crashed,
+            RestartMode::Always => {
+                matches!(reason, CloseReason::Crashed | CloseReason::UserClosed)
+            }
+        }
+    }
+
+    /// Delay before restart number `attempt` (starting at 1), doubling each
+    /// time.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor)).min(MAX_BACKOFF)
+    }
+}
@@ -0,0 +1,447 @@
+use crate::core::proxy_manager::TargetAddr;
+use md5::{Digest, Md5};
+use ring::aead::{Aad, Algorithm, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, CHACHA20_POLY1305};
+use ring::hkdf::{Salt, HKDF_SHA1_FOR_LEGACY_USE_ONLY};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::{Error, ErrorKind, Result};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+const SUBKEY_INFO: &[u8] = b"ss-subkey";
+const TAG_LEN: usize = 16;
+/// Payloads are at most 0x3FFF bytes, the length is masked to 14 bits.
+const MAX_PAYLOAD: usize = 0x3FFF;
+
+/// AEAD ciphers from the Shadowsocks spec; both use 32 byte keys and salts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Gcm,
+    Chacha20IetfPoly1305,
+}
+
+impl Cipher {
+    pub fn from_name(name: &str) -> Option<Cipher> {
+        match name.to_ascii_lowercase().as_str() {
+            "aes-256-gcm" => Some(Cipher::Aes256Gcm),
+            "chacha20-ietf-poly1305" => Some(Cipher::Chacha20IetfPoly1305),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cipher::Aes256Gcm => "aes-256-gcm",
+            Cipher::Chacha20IetfPoly1305 => "chacha20-ietf-poly1305",
+        }
+    }
+
+    fn algorithm(&self) -> &'static Algorithm {
+        match self {
+            Cipher::Aes256Gcm => &AES_256_GCM,
+            Cipher::Chacha20IetfPoly1305 => &CHACHA20_POLY1305,
+        }
+    }
+
+    fn key_len(&self) -> usize {
+        self.algorithm().key_len()
+    }
+
+    /// The master key from a password, OpenSSL's `EVP_BytesToKey` with MD5.
+    fn derive_key(&self, password: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(self.key_len() + 16);
+        let mut last: Vec<u8> = Vec::new();
+        while key.len() < self.key_len() {
+            let mut md5 = Md5::new();
+            md5.update(&last);
+            md5.update(password.as_bytes());
+            last = md5.finalize().to_vec();
+            key.extend_from_slice(&last);
+        }
+        key.truncate(self.key_len());
+        key
+    }
+
+    /// The key for one direction, HKDF-SHA1 of the master key and its salt.
+    fn subkey(&self, key: &[u8], salt: &[u8]) -> LessSafeKey {
+        let prk = Salt::new(HKDF_SHA1_FOR_LEGACY_USE_ONLY, salt).extract(key);
+        let okm = prk
+            .expand(&[SUBKEY_INFO], self.algorithm())
+            .expect("subkey length matches the cipher");
+        LessSafeKey::new(UnboundKey::from(okm))
+    }
+}
+
+/// A subkey and the nonce counter, little-endian from zero and bumped after
+/// every seal or open.
+struct Session {
+    key: LessSafeKey,
+    counter: u64,
+}
+
+impl Session {
+    fn nonce(&mut self) -> Nonce {
+        let mut nonce = [0; 12];
+        nonce[..8].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        Nonce::assume_unique_for_key(nonce)
+    }
+
+    fn seal(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        let mut chunk = data.to_vec();
+        let nonce = self.nonce();
+        self.key
+            .seal_in_place_append_tag(nonce, Aad::empty(), &mut chunk)
+            .expect("chunk fits the cipher");
+        out.extend_from_slice(&chunk);
+    }
+
+    fn open<'a>(&mut self, chunk: &'a mut [u8]) -> Result<&'a mut [u8]> {
+        let nonce = self.nonce();
+        self.key
+            .open_in_place(nonce, Aad::empty(), chunk)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Shadowsocks decryption failed"))
+    }
+}
+
+enum ReadState {
+    Salt,
+    Length,
+    Payload(usize),
+}
+
+/// A TCP stream to a Shadowsocks server. Writes are sealed into chunks of
+/// `[length][tag][payload][tag]` after a random salt; reads are opened the
+/// same way.
+pub struct ShadowsocksStream<S> {
+    inner: S,
+    cipher: Cipher,
+    key: Vec<u8>,
+    encrypt: Session,
+    /// Sealed bytes not yet written to `inner`.
+    pending: Vec<u8>,
+    decrypt: Option<Session>,
+    state: ReadState,
+    /// Ciphertext of the current salt, length or payload, `filled` bytes in.
+    chunk: Vec<u8>,
+    filled: usize,
+    /// Opened payload not yet read.
+    plain: Vec<u8>,
+    plain_pos: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ShadowsocksStream<S> {
+    /// Starts a session to `target` over `inner`, already connected to the
+    /// server. The server does not answer, so a wrong password or cipher
+    /// only shows when it closes the connection.
+    pub async fn connect(
+        inner: S,
+        cipher: Cipher,
+        password: &str,
+        target: &TargetAddr,
+    ) -> Result<ShadowsocksStream<S>> {
+        let key = cipher.derive_key(password);
+        let mut salt = vec![0; cipher.key_len()];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| Error::other("No random salt"))?;
+        let encrypt = Session {
+            key: cipher.subkey(&key, &salt),
+            counter: 0,
+        };
+        let mut stream = ShadowsocksStream {
+            inner,
+            cipher,
+            key,
+            encrypt,
+            pending: salt,
+            decrypt: None,
+            state: ReadState::Salt,
+            chunk: Vec::new(),
+            filled: 0,
+            plain: Vec::new(),
+            plain_pos: 0,
+        };
+        let mut header = Vec::new();
+        target.write_socks5(&mut header)?;
+        stream.seal_chunk(&header);
+        stream.flush().await?;
+        Ok(stream)
+    }
+
+    fn seal_chunk(&mut self, payload: &[u8]) {
+        let len = (payload.len() as u16).to_be_bytes();
+        self.encrypt.seal(&len, &mut self.pending);
+        self.encrypt.seal(payload, &mut self.pending);
+    }
+
+    /// Writes out sealed bytes; ready once nothing is pending.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Reads until the current chunk is complete. Ready with `false` on a
+    /// clean end of stream between chunks.
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool>> {
+        while self.filled < self.chunk.len() {
+            let mut buf = ReadBuf::new(&mut self.chunk[self.filled..]);
+            ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf))?;
+            let read = buf.filled().len();
+            if read == 0 {
+                return if self.filled == 0 && !matches!(self.state, ReadState::Payload(_)) {
+                    Poll::Ready(Ok(false))
+                } else {
+                    Poll::Ready(Err(ErrorKind::UnexpectedEof.into()))
+                };
+            }
+            self.filled += read;
+        }
+        Poll::Ready(Ok(true))
+    }
+
+    fn expect(&mut self, len: usize) {
+        self.chunk.clear();
+        self.chunk.resize(len, 0);
+        self.filled = 0;
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for ShadowsocksStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.plain_pos < this.plain.len() {
+                let len = buf.remaining().min(this.plain.len() - this.plain_pos);
+                buf.put_slice(&this.plain[this.plain_pos..this.plain_pos + len]);
+                this.plain_pos += len;
+                return Poll::Ready(Ok(()));
+            }
+            if this.chunk.is_empty() {
+                let len = match this.state {
+                    ReadState::Salt => this.cipher.key_len(),
+                    ReadState::Length => 2 + TAG_LEN,
+                    ReadState::Payload(len) => len + TAG_LEN,
+                };
+                this.expect(len);
+            }
+            if !ready!(this.poll_chunk(cx))? {
+                return Poll::Ready(Ok(()));
+            }
+            let mut chunk = std::mem::take(&mut this.chunk);
+            match this.state {
+                ReadState::Salt => {
+                    this.decrypt = Some(Session {
+                        key: this.cipher.subkey(&this.key, &chunk),
+                        counter: 0,
+                    });
+                    this.state = ReadState::Length;
+                }
+                ReadState::Length => {
+                    let session = this.decrypt.as_mut().expect("salt read first");
+                    let len = session.open(&mut chunk)?;
+                    let len = u16::from_be_bytes([len[0], len[1]]) as usize & MAX_PAYLOAD;
+                    this.state = ReadState::Payload(len);
+                }
+                ReadState::Payload(_) => {
+                    let session = this.decrypt.as_mut().expect("salt read first");
+                    let payload = session.open(&mut chunk)?.to_vec();
+                    this.plain = payload;
+                    this.plain_pos = 0;
+                    this.state = ReadState::Length;
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for ShadowsocksStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        let len = buf.len().min(MAX_PAYLOAD);
+        this.seal_chunk(&buf[..len]);
+        // The data is taken once sealed; what the socket does not accept now
+        // goes out on the next write or flush.
+        if let Poll::Ready(Err(e)) = this.poll_pending(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, AsyncReadExt, DuplexStream};
+
+    const PASSWORD: &str = "password";
+
+    /// The server's end of a session: opens what the client sealed and seals
+    /// replies under a salt of its own.
+    fn server<S>(inner: S, cipher: Cipher) -> ShadowsocksStream<S> {
+        let key = cipher.derive_key(PASSWORD);
+        let salt = vec![0x5A; cipher.key_len()];
+        ShadowsocksStream {
+            inner,
+            cipher,
+            encrypt: Session {
+                key: cipher.subkey(&key, &salt),
+                counter: 0,
+            },
+            key,
+            pending: salt,
+            decrypt: None,
+            state: ReadState::Salt,
+            chunk: Vec::new(),
+            filled: 0,
+            plain: Vec::new(),
+            plain_pos: 0,
+        }
+    }
+
+    /// Hands out at most one byte per read, so every chunk arrives in pieces.
+    struct OneByte(DuplexStream);
+
+    impl AsyncRead for OneByte {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<Result<()>> {
+            let mut byte = [0; 1];
+            let mut one = ReadBuf::new(&mut byte);
+            ready!(Pin::new(&mut self.0).poll_read(cx, &mut one))?;
+            buf.put_slice(one.filled());
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for OneByte {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize>> {
+            Pin::new(&mut self.0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Pin::new(&mut self.0).poll_shutdown(cx)
+        }
+    }
+
+    /// A salt followed by `payloads` sealed as chunks, as a server sends them.
+    fn sealed(cipher: Cipher, payloads: &[&[u8]]) -> Vec<u8> {
+        let mut stream = server(duplex(1).0, cipher);
+        for payload in payloads {
+            stream.seal_chunk(payload);
+        }
+        stream.pending
+    }
+
+    /// Reads everything `bytes` opens to, as the client does.
+    async fn open(cipher: Cipher, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        let (mut sender, receiver) = duplex(bytes.len().max(1));
+        sender.write_all(&bytes).await?;
+        drop(sender);
+        let mut stream = server(receiver, cipher);
+        let mut plain = Vec::new();
+        stream.read_to_end(&mut plain).await?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn derives_the_master_key_like_openssl() {
+        let key = Cipher::Aes256Gcm.derive_key(PASSWORD);
+        let expected = [
+            0x5f, 0x4d, 0xcc, 0x3b, 0x5a, 0xa7, 0x65, 0xd6, 0x1d, 0x83, 0x27, 0xde, 0xb8, 0x82,
+            0xcf, 0x99, 0x2b, 0x95, 0x99, 0x0a, 0x91, 0x51, 0x37, 0x4a, 0xbd, 0x8f, 0xf8, 0xc5,
+            0xa7, 0xa0, 0xfe, 0x08,
+        ];
+        assert_eq!(key, expected);
+        assert_eq!(Cipher::Chacha20IetfPoly1305.derive_key(PASSWORD), expected);
+    }
+
+    #[tokio::test]
+    async fn round_trips_large_writes_read_a_byte_at_a_time() {
+        for cipher in [Cipher::Aes256Gcm, Cipher::Chacha20IetfPoly1305] {
+            let target = TargetAddr::Domain("example.com".to_string(), 443);
+            let mut header = Vec::new();
+            target.write_socks5(&mut header).unwrap();
+            let data: Vec<u8> = (0..3 * MAX_PAYLOAD + 7).map(|i| i as u8).collect();
+
+            let (client_end, server_end) = duplex(4096);
+            let mut server = server(OneByte(server_end), cipher);
+            let sent = data.clone();
+            let client = tokio::spawn(async move {
+                let mut client = ShadowsocksStream::connect(client_end, cipher, PASSWORD, &target)
+                    .await
+                    .unwrap();
+                client.write_all(&sent).await.unwrap();
+                client.flush().await.unwrap();
+                let mut reply = [0; 4];
+                client.read_exact(&mut reply).await.unwrap();
+                assert_eq!(&reply, b"pong");
+            });
+
+            let mut received = vec![0; header.len() + data.len()];
+            server.read_exact(&mut received).await.unwrap();
+            assert_eq!(received[..header.len()], header);
+            assert!(received[header.len()..] == data);
+            server.write_all(b"pong").await.unwrap();
+            server.flush().await.unwrap();
+            client.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_a_tampered_tag() {
+        let mut bytes = sealed(Cipher::Aes256Gcm, &[b"hello"]);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        let error = open(Cipher::Aes256Gcm, bytes).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_truncated_chunk() {
+        let mut bytes = sealed(Cipher::Aes256Gcm, &[b"hello"]);
+        bytes.truncate(bytes.len() - 3);
+        let error = open(Cipher::Aes256Gcm, bytes).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn ends_cleanly_between_chunks() {
+        let bytes = sealed(Cipher::Chacha20IetfPoly1305, &[b"hello", b" world"]);
+        let plain = open(Cipher::Chacha20IetfPoly1305, bytes).await.unwrap();
+        assert_eq!(plain, b"hello world");
+    }
+}
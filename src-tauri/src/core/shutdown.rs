@@ -0,0 +1,143 @@
+use crate::core::process::ChromeProcess;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShutdownOptions {
+    /// How long to wait for Chrome to exit after `Browser.close`.
+    pub cdp_timeout_ms: u64,
+    /// How long to wait for Chrome to exit after SIGTERM before SIGKILL.
+    pub term_timeout_ms: u64,
+}
+
+impl Default for ShutdownOptions {
+    fn default() -> Self {
+        ShutdownOptions {
+            cdp_timeout_ms: 5000,
+            term_timeout_ms: 3000,
+        }
+    }
+}
+
+/// The step of the shutdown sequence that made Chrome exit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownStep {
+    AlreadyExited,
+    BrowserClose,
+    Terminate,
+    Kill,
+}
+
+async fn send_browser_close(ws: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (mut socket, _) = connect_async(ws).await?;
+    let request = json!({ "id": 1, "method": "Browser.close" });
+    socket
+        .send(Message::Text(request.to_string().into()))
+        .await?;
+    // Chrome acknowledges the command and then drops the connection while
+    // exiting, so either one means the request went through.
+    while let Some(message) = socket.next().await {
+        match message? {
+            Message::Text(text) => {
+                let reply: Value = serde_json::from_str(&text)?;
+                if reply.get("id") == Some(&json!(1)) {
+                    if let Some(error) = reply.get("error") {
+                        return Err(format!("Browser.close rejected: {}", error).into());
+                    }
+                    break;
+                }
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+async fn wait_for_exit(process: &ChromeProcess, limit: Duration) -> bool {
+    timeout(limit, process.wait()).await.is_ok()
+}
+
+/// Closes Chrome as gently as possible: CDP `Browser.close` first so the
+/// profile is flushed and no "restore pages" bubble shows up next time, then
+/// SIGTERM, then SIGKILL. Whatever is left of the process tree is swept
+/// afterwards.
+pub async fn shutdown_chrome(
+    process: &ChromeProcess,
+    ws: Option<&str>,
+    options: &ShutdownOptions,
+) -> std::io::Result<ShutdownStep> {
+    process.mark_closing();
+    let step = if !process.is_running() {
+        ShutdownStep::AlreadyExited
+    } else if let Some(ws) = ws.filter(|_| options.cdp_timeout_ms > 0) {
+        let cdp_timeout = Duration::from_millis(options.cdp_timeout_ms);
+        eprintln!("Sending Browser.close to PID: {}", process.pid());
+        let closed = match timeout(cdp_timeout, send_browser_close(ws)).await {
+            Ok(Ok(())) => wait_for_exit(process, cdp_timeout).await,
+            Ok(Err(e)) => {
+                eprintln!("Browser.close failed: {}", e);
+                false
+            }
+            Err(_) => {
+                eprintln!("Browser.close timed out");
+                false
+            }
+        };
+        if closed {
+            ShutdownStep::BrowserClose
+        } else {
+            escalate(process, options).await?
+        }
+    } else {
+        escalate(process, options).await?
+    };
+    // Once the browser has been reaped its process group may be reused; the
+    // exit watcher already swept it before reaping, since we marked it.
+    if process.exit_info().is_none() {
+        process.kill_tree()?;
+    }
+    eprintln!("Chrome PID: {} closed by {:?}", process.pid(), step);
+    Ok(step)
+}
+
+async fn escalate(
+    process: &ChromeProcess,
+    options: &ShutdownOptions,
+) -> std::io::Result<ShutdownStep> {
+    if process.terminate()? {
+        let term_timeout = Duration::from_millis(options.term_timeout_ms);
+        if wait_for_exit(process, term_timeout).await {
+            return Ok(ShutdownStep::Terminate);
+        }
+    }
+    process.kill_tree()?;
+    Ok(ShutdownStep::Kill)
+}
+
+/// Resolves on SIGTERM or Ctrl-C, for processes that have to close their
+/// instances before exiting.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = term.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => eprintln!("Listen for SIGTERM failed: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
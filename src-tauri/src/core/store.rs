@@ -0,0 +1,330 @@
+use crate::core::error::ShadowError;
+use crate::core::host::host;
+use crate::core::instances::StartingDir;
+use crate::core::proxy_manager::{ProxyChain, ProxyServer, ProxyStatus};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Row};
+use std::cmp::Ordering;
+use std::fs;
+
+#[derive(Serialize, Deserialize)]
+pub struct PageInfo {
+    pub page: u16,
+    pub page_size: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PageResult<T> {
+    pub list: Vec<T>,
+    pub page: u16,
+    pub page_size: u16,
+    pub total: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CommonInfo {
+    pub id: u16,
+    pub name: String,
+    pub remark: String,
+}
+
+/// A group or proxy to add.
+#[derive(Serialize, Deserialize)]
+pub struct NewCommonInfo {
+    pub name: String,
+    pub remark: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IdInfo {
+    pub id: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DeletedInfo {
+    pub id: u16,
+    pub name: String,
+}
+
+pub async fn query_groups(info: &PageInfo) -> Result<PageResult<CommonInfo>, ShadowError> {
+    query_common("group_table", info).await
+}
+
+/// Pages through a table of named rows: groups or proxies.
+async fn query_common(table: &str, info: &PageInfo) -> Result<PageResult<CommonInfo>, ShadowError> {
+    let pool = host().db().await?;
+    let sql = format!(
+        "SELECT *, COUNT(*) OVER () AS total FROM {} LIMIT {} OFFSET {}",
+        table,
+        info.page_size,
+        info.page.saturating_sub(1) * info.page_size
+    );
+    let query = sqlx::query(&sql);
+    let rows = pool.fetch_all(query).await?;
+    let mut values = Vec::new();
+    let mut total = 0;
+    for row in rows {
+        total = row.try_get("total")?;
+        let id: u16 = row.try_get("id")?;
+        let name: String = row.try_get("name")?;
+        let remark: Option<String> = row.try_get("remark")?;
+        let remark = remark.unwrap_or_default();
+        values.push(CommonInfo { id, name, remark });
+    }
+    Ok(PageResult {
+        page: info.page,
+        page_size: info.page_size,
+        list: values,
+        total,
+    })
+}
+
+/// Inserts a named row into `table`, returning it.
+async fn insert_common(table: &str, info: NewCommonInfo) -> Result<CommonInfo, ShadowError> {
+    let pool = host().db().await?;
+    let remark = info.remark.unwrap_or_default();
+    let sql = format!("INSERT INTO {} (name, remark) VALUES (?, ?)", table);
+    let query = sqlx::query(&sql).bind(&info.name).bind(&remark);
+    let result = pool.execute(query).await?;
+    Ok(CommonInfo {
+        id: result.last_insert_rowid() as u16,
+        name: info.name,
+        remark,
+    })
+}
+
+/// Deletes row `id` from `table`; profiles using it keep running without it.
+async fn delete_common(table: &str, id: u16) -> Result<DeletedInfo, ShadowError> {
+    let pool = host().db().await?;
+    let sql = format!("DELETE FROM {} WHERE id = ? RETURNING name", table);
+    let query = sqlx::query(&sql).bind(id);
+    let row = pool
+        .fetch_optional(query)
+        .await?
+        .ok_or(ShadowError::InvalidArgument(format!(
+            "No row {} in {}",
+            id, table
+        )))?;
+    Ok(DeletedInfo {
+        id,
+        name: row.try_get("name")?,
+    })
+}
+
+pub async fn create_group(info: NewCommonInfo) -> Result<CommonInfo, ShadowError> {
+    let group = insert_common("group_table", info).await?;
+    host().emit("group-update", ());
+    Ok(group)
+}
+
+pub async fn delete_group(info: IdInfo) -> Result<DeletedInfo, ShadowError> {
+    let group = delete_common("group_table", info.id).await?;
+    host().emit("group-update", ());
+    host().emit("profile-refresh", ());
+    Ok(group)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupInfo {
+    pub page: u16,
+    pub page_size: u16,
+    pub group_id: i16, //-1:ungruped;0:all
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProfileInfo {
+    pub id: u16,
+    pub name: String,
+    pub remark: String,
+    pub group_name: String,
+    pub proxy_name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewProfileInfo {
+    pub name: String,
+    pub group_id: Option<u16>,
+    pub proxy_id: Option<u16>,
+    pub remark: Option<String>,
+}
+
+pub async fn query_profiles(info: &GroupInfo) -> Result<PageResult<ProfileInfo>, ShadowError> {
+    let pool = host().db().await?;
+    let mut sql = "SELECT p.id, p.name, p.remark, COUNT(*) OVER () AS total, COALESCE(g.name, 'ungrouped') AS group_name, COALESCE(pr.name, 'unproxied') AS proxy_name FROM profile_table p LEFT JOIN group_table g ON p.group_id = g.id LEFT JOIN proxy_table pr ON p.proxy_id = pr.id".to_owned();
+    let offset = info.page.saturating_sub(1) * info.page_size;
+    match info.group_id.cmp(&0) {
+        Ordering::Less => {
+            sql += format!(
+                " WHERE p.group_id IS NULL LIMIT {} OFFSET {}",
+                info.page_size, offset
+            )
+            .as_str();
+        }
+        Ordering::Greater => {
+            sql += format!(
+                " WHERE p.group_id = {} LIMIT {} OFFSET {}",
+                info.group_id, info.page_size, offset
+            )
+            .as_str();
+        }
+        Ordering::Equal => {
+            sql += format!(" LIMIT {} OFFSET {}", info.page_size, offset).as_str();
+        }
+    };
+    let query = sqlx::query(&sql);
+    let rows = pool.fetch_all(query).await?;
+    let mut values = Vec::new();
+    let mut total = 0;
+    for row in rows {
+        total = row.try_get("total")?;
+        let id: u16 = row.try_get("id")?;
+        let name: String = row.try_get("name")?;
+        let remark: Option<String> = row.try_get("remark")?;
+        let remark = remark.unwrap_or_default();
+        let group_name = row.try_get("group_name")?;
+        let proxy_name = row.try_get("proxy_name")?;
+        values.push(ProfileInfo {
+            id,
+            name,
+            remark,
+            group_name,
+            proxy_name,
+        });
+    }
+    Ok(PageResult {
+        page: info.page,
+        page_size: info.page_size,
+        list: values,
+        total,
+    })
+}
+
+pub async fn create_profile(info: NewProfileInfo) -> Result<CommonInfo, ShadowError> {
+    let pool = host().db().await?;
+    let remark = info.remark.unwrap_or_default();
+    let sql = "INSERT INTO profile_table (name, group_id, proxy_id, remark) VALUES (?, ?, ?, ?)";
+    let query = sqlx::query(sql)
+        .bind(&info.name)
+        .bind(info.group_id)
+        .bind(info.proxy_id)
+        .bind(&remark);
+    let result = pool.execute(query).await?;
+    host().emit("profile-refresh", ());
+    Ok(CommonInfo {
+        id: result.last_insert_rowid() as u16,
+        name: info.name,
+        remark,
+    })
+}
+
+/// Deletes a profile that is not running, together with its user data dir.
+pub async fn delete_profile(info: IdInfo) -> Result<DeletedInfo, ShadowError> {
+    let pool = host().db().await?;
+    let query = sqlx::query("SELECT name FROM profile_table WHERE id = ?").bind(info.id);
+    let row = pool
+        .fetch_optional(query)
+        .await?
+        .ok_or(ShadowError::ProfileNotFound { id: info.id })?;
+    let name: String = row.try_get("name")?;
+    let user_dir = host().profiles_dir().join(&name);
+    // Held until the dir is gone, so the profile cannot be launched meanwhile.
+    let Some(_claim) = StartingDir::claim(&user_dir.to_string_lossy()).await else {
+        return Err(ShadowError::ProfileLocked {
+            user_dir: user_dir.to_string_lossy().to_string(),
+        });
+    };
+    let sql = "DELETE FROM profile_table WHERE id = ?";
+    pool.execute(sqlx::query(sql).bind(info.id)).await?;
+    if user_dir.exists() {
+        fs::remove_dir_all(&user_dir).map_err(|e| ShadowError::Internal(e.to_string()))?;
+    }
+    host().emit("profile-refresh", ());
+    Ok(DeletedInfo { id: info.id, name })
+}
+
+pub async fn query_proxies(info: &PageInfo) -> Result<PageResult<CommonInfo>, ShadowError> {
+    query_common("proxy_table", info).await
+}
+
+/// A stored proxy by `id`, or any proxy by `url`.
+#[derive(Serialize, Deserialize)]
+pub struct ProxyCheckInfo {
+    pub id: Option<u16>,
+    pub url: Option<String>,
+}
+
+pub async fn check_proxy(info: ProxyCheckInfo) -> Result<ProxyStatus, ShadowError> {
+    let url = match (info.id, info.url) {
+        (Some(id), _) => {
+            let pool = host().db().await?;
+            let query = sqlx::query("SELECT name FROM proxy_table WHERE id = ?").bind(id);
+            let row = pool
+                .fetch_optional(query)
+                .await?
+                .ok_or(ShadowError::InvalidArgument(format!("No proxy {}", id)))?;
+            row.try_get("name")?
+        }
+        (None, Some(url)) => url,
+        (None, None) => {
+            return Err(ShadowError::InvalidArgument(
+                "Proxy id or url required".to_string(),
+            ))
+        }
+    };
+    let proxy = ProxyChain::from_url(&url).map_err(ShadowError::InvalidProxy)?;
+    ProxyServer::check_proxy(proxy)
+        .await
+        .map_err(|e| ShadowError::ProxyUnreachable(e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportInfo {
+    /// Proxy URLs in the format the proxies page accepts, chains as hop
+    /// URLs joined by `|`.
+    pub proxies: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RejectedProxy {
+    pub proxy: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportResult {
+    pub imported: Vec<CommonInfo>,
+    /// Already stored, left untouched.
+    pub skipped: Vec<String>,
+    pub rejected: Vec<RejectedProxy>,
+}
+
+pub async fn import_proxies(info: ImportInfo) -> Result<ImportResult, ShadowError> {
+    let pool = host().db().await?;
+    let mut result = ImportResult {
+        imported: Vec::new(),
+        skipped: Vec::new(),
+        rejected: Vec::new(),
+    };
+    for proxy in info.proxies {
+        if let Err(error) = ProxyChain::from_url(&proxy) {
+            result.rejected.push(RejectedProxy { proxy, error });
+            continue;
+        }
+        let query = sqlx::query("INSERT OR IGNORE INTO proxy_table (name, remark) VALUES (?, '')")
+            .bind(&proxy);
+        let inserted = pool.execute(query).await?;
+        if inserted.rows_affected() == 0 {
+            result.skipped.push(proxy);
+        } else {
+            result.imported.push(CommonInfo {
+                id: inserted.last_insert_rowid() as u16,
+                name: proxy,
+                remark: String::new(),
+            });
+        }
+    }
+    if !result.imported.is_empty() {
+        host().emit("proxy-update", ());
+    }
+    Ok(result)
+}
@@ -0,0 +1,46 @@
+use crate::core::host::host;
+use crate::core::instances::close_all;
+use crate::core::open_app_data;
+use crate::core::shutdown::{shutdown_signal, ShutdownOptions};
+use crate::server;
+use std::thread;
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+
+/// Serves the HTTP API without the webview, on the database, settings and
+/// profiles of the GUI. Runs until SIGTERM or Ctrl-C, then closes every
+/// instance and relay it manages.
+pub fn run_daemon() {
+    let runtime = Runtime::new().expect("failed to start tokio runtime");
+    if let Err(e) = runtime.block_on(serve()) {
+        eprintln!("Shadow daemon failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn serve() -> Result<(), String> {
+    // Nobody listens to events in the daemon; they are only logged.
+    open_app_data(|event: &str, payload| eprintln!("Event {}: {}", event, payload))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let (stopped_tx, stopped_rx) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = stopped_tx.send(server::init());
+    });
+    eprintln!(
+        "Shadow daemon running, data in {}",
+        host().data_dir().display()
+    );
+    let result = tokio::select! {
+        _ = shutdown_signal() => Ok(()),
+        stopped = stopped_rx => match stopped {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(format!("API server failed: {}", e)),
+            Err(_) => Err("API server thread panicked".to_string()),
+        },
+    };
+    eprintln!("Shutting down, closing instances.");
+    close_all(ShutdownOptions::default()).await;
+    result
+}
@@ -0,0 +1,219 @@
+use crate::core::args::{LaunchExtras, LaunchMode};
+use crate::core::browser::{installed_browsers, InstalledBrowser};
+use crate::core::db::{self, DB_URL};
+use crate::core::devtools::DEFAULT_STARTUP_TIMEOUT_MS;
+use crate::core::error::ShadowError;
+use crate::core::host::{host, Database, Host};
+use crate::core::instances::{
+    self, relay_addr, release_relay, resolve_browser, start_instance, ChromeClosed, ChromeInstance,
+    ChromeLaunchInfo, LaunchSpec,
+};
+use crate::core::ports::{PortLeaseInfo, DEBUG_PORTS};
+use crate::core::profile::{self, LaunchOverrides};
+use crate::core::proxy_manager::{Proxy, ProxyChain, ProxyServer, ProxyStatus, RELAYS};
+use crate::core::reattach::reattach_instances;
+use crate::core::restart::RestartPolicy;
+use crate::core::shutdown::ShutdownOptions;
+use futures_util::future::BoxFuture;
+use sqlx::{Pool, Sqlite};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_sql::{DbInstances, DbPool, Migration, MigrationKind};
+use tokio::task;
+
+/// The pool tauri-plugin-sql opened for the frontend.
+struct PluginDatabase(AppHandle);
+
+impl Database for PluginDatabase {
+    fn pool(&self) -> BoxFuture<'_, Result<Pool<Sqlite>, ShadowError>> {
+        Box::pin(async move {
+            let PluginDatabase(app) = self;
+            let instances_state = app.try_state::<DbInstances>().ok_or(ShadowError::Database(
+                "database plugin not loaded".to_string(),
+            ))?;
+            let instances = instances_state.0.read().await;
+            match instances.get(DB_URL) {
+                Some(DbPool::Sqlite(pool)) => Ok(pool.clone()),
+                None => Err(ShadowError::Database(format!("{} not loaded", DB_URL))),
+            }
+        })
+    }
+}
+
+#[tauri::command]
+async fn list_chrome_instances() -> Vec<ChromeInstance> {
+    instances::list_chrome_instances().await
+}
+
+#[tauri::command]
+async fn launch_profile(
+    id: u16,
+    overrides: Option<LaunchOverrides>,
+) -> Result<ChromeLaunchInfo, ShadowError> {
+    profile::launch_profile(id, overrides.unwrap_or_default()).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn launch_chrome(
+    id: u16,
+    user_dir: String,
+    port: Option<u16>,
+    proxy: Option<String>,
+    browser_path: Option<String>,
+    win_chrome_path: Option<String>,
+    startup_timeout_ms: Option<u64>,
+    restart: Option<RestartPolicy>,
+    mode: Option<LaunchMode>,
+    display_pool: Option<String>,
+    extras: Option<LaunchExtras>,
+    overrides: Option<LaunchExtras>,
+) -> Result<ChromeLaunchInfo, ShadowError> {
+    let extras = extras
+        .unwrap_or_default()
+        .merge(overrides.unwrap_or_default());
+    extras.validate()?;
+    let chrome_path = resolve_browser(browser_path, win_chrome_path)?;
+    let relay = match proxy.as_deref().and_then(relay_addr) {
+        Some(addr) => RELAYS.lease(addr).await,
+        None => None,
+    };
+    // Only a relay this launch leased is given back if it fails.
+    let leased = relay.as_ref().and(proxy.clone());
+    let spec = LaunchSpec {
+        id,
+        user_dir,
+        port,
+        proxy,
+        relay,
+        chrome_path,
+        mode: mode.unwrap_or_default(),
+        display_pool,
+        extras,
+        startup_timeout: Duration::from_millis(
+            startup_timeout_ms.unwrap_or(DEFAULT_STARTUP_TIMEOUT_MS),
+        ),
+        restart: restart.unwrap_or_default(),
+    };
+    let result = start_instance(spec, 0, true).await;
+    if result.is_err() {
+        release_relay(leased.as_deref()).await;
+    }
+    result
+}
+
+#[tauri::command]
+async fn close_chrome(
+    pid: u32,
+    options: Option<ShutdownOptions>,
+) -> Result<ChromeClosed, ShadowError> {
+    instances::close_instance(pid, options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn list_browsers(refresh: Option<bool>) -> Result<Vec<InstalledBrowser>, ShadowError> {
+    task::spawn_blocking(move || installed_browsers(refresh.unwrap_or(false)))
+        .await
+        .map_err(|e| ShadowError::Internal(format!("List browsers failed: {}", e)))
+}
+
+#[tauri::command]
+async fn list_port_leases() -> Vec<PortLeaseInfo> {
+    DEBUG_PORTS.leases()
+}
+
+#[tauri::command]
+fn validate_launch_extras(extras: LaunchExtras) -> Result<(), ShadowError> {
+    extras.validate()
+}
+
+#[tauri::command]
+async fn start_proxy(proxy: ProxyChain) -> Result<SocketAddr, ShadowError> {
+    RELAYS
+        .create_server(proxy)
+        .await
+        .map_err(|e| ShadowError::ProxyRelayFailed(e.to_string()))
+}
+
+#[tauri::command]
+async fn stop_proxy(proxy: Proxy) -> Result<(), ShadowError> {
+    match RELAYS.stop_server(&proxy).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(ShadowError::ProxyRelayNotFound),
+        Err(e) => Err(ShadowError::ProxyRelayFailed(e.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn check_proxy(proxy: ProxyChain) -> Result<ProxyStatus, ShadowError> {
+    ProxyServer::check_proxy(proxy)
+        .await
+        .map_err(|e| ShadowError::ProxyUnreachable(e.to_string()))
+}
+
+#[tauri::command]
+async fn list_proxy() -> Result<Vec<ProxyServer>, ShadowError> {
+    let proxies = RELAYS.servers().await;
+    Ok(proxies)
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .setup(|app| {
+            let handle = app.handle().clone();
+            crate::core::init(Host::new(
+                move |event: &str, payload| {
+                    let _ = handle.emit(event, payload);
+                },
+                PluginDatabase(app.handle().clone()),
+                app.path().app_data_dir()?,
+            ));
+            #[cfg(feature = "api-server")]
+            std::thread::spawn(move || {
+                crate::server::init().unwrap();
+            });
+            tauri::async_runtime::spawn(reattach_instances(host().profiles_dir()));
+            Ok(())
+        })
+        .plugin(tauri_plugin_window_state::Builder::new().build())
+        .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(
+            tauri_plugin_sql::Builder::default()
+                .add_migrations(
+                    DB_URL,
+                    db::MIGRATIONS
+                        .iter()
+                        .map(|&(version, description, sql)| Migration {
+                            version,
+                            description,
+                            sql,
+                            kind: MigrationKind::Up,
+                        })
+                        .collect(),
+                )
+                .build(),
+        )
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_opener::init())
+        .invoke_handler(tauri::generate_handler![
+            launch_profile,
+            launch_chrome,
+            close_chrome,
+            list_chrome_instances,
+            list_browsers,
+            list_port_leases,
+            validate_launch_extras,
+            check_proxy,
+            start_proxy,
+            stop_proxy,
+            list_proxy
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -2,5 +2,16 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    #[cfg(feature = "api-server")]
+    if args.iter().any(|arg| arg == "--daemon") {
+        return shadow_lib::run_daemon();
+    }
+    // Without the GUI there is nothing else to start, so everything goes to
+    // the CLI, which prints its usage when no command is given.
+    if !cfg!(feature = "gui") || shadow_lib::is_cli_command(&args) {
+        std::process::exit(shadow_lib::run_cli(args))
+    }
+    #[cfg(feature = "gui")]
     shadow_lib::run()
 }
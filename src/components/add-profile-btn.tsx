@@ -24,24 +24,46 @@ import {
     PopoverTrigger,
 } from "@/components/ui/popover"
 import { Input } from "@/components/ui/input"
+import { Textarea } from "@/components/ui/textarea"
 import { Label } from "./ui/label"
 import { useEffect, useRef, useState } from "react"
-import { cn, createLocalProfile } from "@/lib/utils"
+import { cn, createLocalProfile, errorMessage, formatEnvText, parseArgsText, parseEnvText } from "@/lib/utils"
 import { addProfile, checkProfile, getGroups, getProxies, updateProfile } from "@/lib/db-service"
-import { GroupType, ProfileType, ProxyType } from "@/lib/types"
+import { GroupType, InstalledBrowserType, LaunchExtrasType, LaunchModeType, ProfileSettingsType, ProfileType, ProxyType, RestartPolicyType } from "@/lib/types"
 import { toast } from "sonner"
 import { emit, listen } from "@tauri-apps/api/event"
+import { invoke } from "@tauri-apps/api/core"
+import {
+    Select,
+    SelectContent,
+    SelectItem,
+    SelectTrigger,
+    SelectValue,
+} from "@/components/ui/select"
 import { GROUP_UPDATE_EVENT_NAME, PROFILE_EDIT_EVENT_NAME, PROFILE_REFRESH_EVENT_NAME, PROXY_UPDATE_EVENT_NAME } from "@/lib/consts"
 import { BulkProfileBtn } from "./bulk-profile-btn"
 
+type ProfileInfo = {
+    name: string, groupId: number | undefined, groupOpen: boolean, proxyId: number | undefined, proxyOpen: boolean, browserPath: string | undefined,
+    restartPolicy: RestartPolicyType, restartMaxRetries: number, restartBackoffMs: number, launchMode: LaunchModeType, extraArgs: string, extraEnv: string, remark: string
+}
+
+const EMPTY_INFO: ProfileInfo = {
+    name: '', groupId: undefined, groupOpen: false, proxyId: undefined, proxyOpen: false, browserPath: undefined,
+    restartPolicy: 'never', restartMaxRetries: 3, restartBackoffMs: 1000, launchMode: 'headed', extraArgs: '', extraEnv: '', remark: ''
+}
+
+const RESTART_POLICIES: Array<RestartPolicyType> = ['never', 'on_crash', 'always']
+
+const LAUNCH_MODES: Array<LaunchModeType> = ['headed', 'headless', 'virtual_display']
+
 export const AddProfileBtn = () => {
     const { t } = useTranslation()
     const [open, setOpen] = useState(false)
-    const [info, setInfo] = useState<{
-        name: string, groupId: number | undefined, groupOpen: boolean, proxyId: number | undefined, proxyOpen: boolean, remark: string
-    }>({ name: '', groupId: undefined, groupOpen: false, proxyId: undefined, proxyOpen: false, remark: '' })
+    const [info, setInfo] = useState<ProfileInfo>(EMPTY_INFO)
     const [groupInfos, setGroupInfos] = useState<Array<GroupType>>([])
     const [proxyInfos, setProxyInfos] = useState<Array<ProxyType>>([])
+    const [browserInfos, setBrowserInfos] = useState<Array<InstalledBrowserType>>([])
     const unlistenProfileRef = useRef<(() => void) | null>(null)
     const unlistenGroupRef = useRef<(() => void) | null>(null)
     const unlistenProxyRef = useRef<(() => void) | null>(null)
@@ -54,6 +76,8 @@ export const AddProfileBtn = () => {
             setGroupInfos(groupResult)
             const proxyResult = await getProxies()
             setProxyInfos(proxyResult)
+            const browserResult: Array<InstalledBrowserType> = await invoke('list_browsers')
+            setBrowserInfos(browserResult)
         })()
         const setupListener = async () => {
             unlistenProfileRef.current = await listen(PROFILE_EDIT_EVENT_NAME, async ({ payload }) => {
@@ -68,6 +92,13 @@ export const AddProfileBtn = () => {
                     name: editInfo.name,
                     groupId: groupInfos.find(gi => gi.name == editInfo.group_name)?.id,
                     proxyId: proxyInfos.find(pi => pi.name == editInfo.proxy_name)?.id,
+                    browserPath: editInfo.browser_path ?? undefined,
+                    restartPolicy: editInfo.restart_policy,
+                    restartMaxRetries: editInfo.restart_max_retries,
+                    restartBackoffMs: editInfo.restart_backoff_ms,
+                    launchMode: editInfo.launch_mode,
+                    extraArgs: editInfo.extra_args ? (JSON.parse(editInfo.extra_args) as string[]).join('\n') : '',
+                    extraEnv: editInfo.extra_env ? formatEnvText(JSON.parse(editInfo.extra_env)) : '',
                     remark: editInfo.remark ?? ''
                 }))
                 setEditMode(true)
@@ -98,7 +129,7 @@ export const AddProfileBtn = () => {
         }
     }, [])
 
-    const handleChange = (e: React.ChangeEvent<HTMLInputElement>) => {
+    const handleChange = (e: React.ChangeEvent<HTMLInputElement | HTMLTextAreaElement>) => {
         const { name, value } = e.target;
         setInfo((prev) => ({
             ...prev,
@@ -106,35 +137,63 @@ export const AddProfileBtn = () => {
         }));
     };
 
+    const handleNumberChange = (e: React.ChangeEvent<HTMLInputElement>) => {
+        const { name, value } = e.target;
+        const num = parseInt(value, 10)
+        setInfo((prev) => ({
+            ...prev,
+            [name]: isNaN(num) || num < 0 ? 0 : num
+        }));
+    };
+
     const handleSave = async () => {
         console.log({ info })
+        const extras: LaunchExtrasType = {
+            args: parseArgsText(info.extraArgs),
+            env: parseEnvText(info.extraEnv),
+        }
+        const settings: ProfileSettingsType = {
+            browser_path: info.browserPath ?? null,
+            restart_policy: info.restartPolicy,
+            restart_max_retries: info.restartMaxRetries,
+            restart_backoff_ms: info.restartBackoffMs,
+            launch_mode: info.launchMode,
+            extra_args: extras.args.length > 0 ? JSON.stringify(extras.args) : null,
+            extra_env: Object.keys(extras.env).length > 0 ? JSON.stringify(extras.env) : null,
+        }
         if (!info.name) {
             toast.warning(t("empty_name"))
             return
         }
+        try {
+            await invoke('validate_launch_extras', { extras })
+        } catch (error: any) {
+            toast.warning(errorMessage(error))
+            return
+        }
         if (!editMode) {
             const exist = await checkProfile(info.name)
             if (exist) {
                 toast.warning(t("profile_exist"))
             } else {
                 await createLocalProfile(info.name)
-                const { rowsAffected } = await addProfile(info.name, info.groupId ?? null, info.proxyId ?? null, info.remark ?? null)
+                const { rowsAffected } = await addProfile(info.name, info.groupId ?? null, info.proxyId ?? null, settings, info.remark ?? null)
                 if (rowsAffected == 1) {
                     toast.success(t("add_profile_success"))
                     setOpen(false)
                     await emit(PROFILE_REFRESH_EVENT_NAME, { jumpLast: true })
-                    setInfo(({ name: '', groupId: undefined, groupOpen: false, proxyId: undefined, proxyOpen: false, remark: '' }))
+                    setInfo(EMPTY_INFO)
                 } else {
                     toast.warning(t("add_profile_failed"))
                 }
             }
         } else {
-            const { rowsAffected } = await updateProfile(editProfileId.current, info.name, info.groupId ?? null, info.proxyId ?? null, info.remark ?? null)
+            const { rowsAffected } = await updateProfile(editProfileId.current, info.name, info.groupId ?? null, info.proxyId ?? null, settings, info.remark ?? null)
             if (rowsAffected == 1) {
                 toast.success(t("update_profile_success"))
                 setOpen(false)
                 await emit(PROFILE_REFRESH_EVENT_NAME)
-                setInfo(({ name: '', groupId: undefined, groupOpen: false, proxyId: undefined, proxyOpen: false, remark: '' }))
+                setInfo(EMPTY_INFO)
             } else {
                 toast.warning(t("update_profile_failed"))
             }
@@ -146,7 +205,7 @@ export const AddProfileBtn = () => {
             <DialogTrigger asChild>
                 <div>
                     <Button className='rounded-none rounded-l-md' onClick={() => {
-                        setInfo(({ name: '', groupId: undefined, groupOpen: false, proxyId: undefined, proxyOpen: false, remark: '' }))
+                        setInfo(EMPTY_INFO)
                         setEditMode(false)
                     }}>
                         <span className="text-xs">{t('new_profile')}</span>
@@ -290,6 +349,124 @@ export const AddProfileBtn = () => {
                             </PopoverContent>
                         </Popover>
                     </div>
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="browser" className="text-right">
+                            {t('browser')}
+                        </Label>
+                        <Select value={info.browserPath ?? 'default'} onValueChange={(value: string) => {
+                            setInfo(prev => ({
+                                ...prev,
+                                browserPath: value == 'default' ? undefined : value
+                            }))
+                        }}>
+                            <SelectTrigger id="browser" className="col-span-3">
+                                <SelectValue />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="default">{t('default_browser')}</SelectItem>
+                                {browserInfos.map(b => (
+                                    <SelectItem value={b.path} key={b.path}>
+                                        {b.version ?? b.kind} ({b.channel})
+                                    </SelectItem>
+                                ))}
+                                {info.browserPath && !browserInfos.find(b => b.path == info.browserPath) && (
+                                    <SelectItem value={info.browserPath}>{info.browserPath}</SelectItem>
+                                )}
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="launchMode" className="text-right">
+                            {t('launch_mode')}
+                        </Label>
+                        <Select value={info.launchMode} onValueChange={(value: string) => {
+                            setInfo(prev => ({
+                                ...prev,
+                                launchMode: value as LaunchModeType
+                            }))
+                        }}>
+                            <SelectTrigger id="launchMode" className="col-span-3">
+                                <SelectValue />
+                            </SelectTrigger>
+                            <SelectContent>
+                                {LAUNCH_MODES.map(mode => (
+                                    <SelectItem value={mode} key={mode}>{t(`launch_mode_${mode}`)}</SelectItem>
+                                ))}
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="restart" className="text-right">
+                            {t('restart')}
+                        </Label>
+                        <Select value={info.restartPolicy} onValueChange={(value: string) => {
+                            setInfo(prev => ({
+                                ...prev,
+                                restartPolicy: value as RestartPolicyType
+                            }))
+                        }}>
+                            <SelectTrigger id="restart" className="col-span-3">
+                                <SelectValue />
+                            </SelectTrigger>
+                            <SelectContent>
+                                {RESTART_POLICIES.map(policy => (
+                                    <SelectItem value={policy} key={policy}>{t(`restart_${policy}`)}</SelectItem>
+                                ))}
+                            </SelectContent>
+                        </Select>
+                    </div>
+                    {info.restartPolicy != 'never' && (
+                        <div className="grid grid-cols-4 items-center gap-4">
+                            <Label htmlFor="restartMaxRetries" className="text-right">
+                                {t('max_retries')}
+                            </Label>
+                            <Input
+                                id="restartMaxRetries"
+                                name="restartMaxRetries"
+                                type="number"
+                                min={0}
+                                value={info.restartMaxRetries}
+                                onChange={handleNumberChange}
+                            />
+                            <Label htmlFor="restartBackoffMs" className="text-right">
+                                {t('backoff_ms')}
+                            </Label>
+                            <Input
+                                id="restartBackoffMs"
+                                name="restartBackoffMs"
+                                type="number"
+                                min={0}
+                                value={info.restartBackoffMs}
+                                onChange={handleNumberChange}
+                            />
+                        </div>
+                    )}
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="extraArgs" className="text-right">
+                            {t('extra_args')}
+                        </Label>
+                        <Textarea
+                            id="extraArgs"
+                            name="extraArgs"
+                            className="col-span-3"
+                            placeholder={t("extra_args_hint")}
+                            value={info.extraArgs}
+                            onChange={handleChange}
+                        />
+                    </div>
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="extraEnv" className="text-right">
+                            {t('extra_env')}
+                        </Label>
+                        <Textarea
+                            id="extraEnv"
+                            name="extraEnv"
+                            className="col-span-3"
+                            placeholder={t("extra_env_hint")}
+                            value={info.extraEnv}
+                            onChange={handleChange}
+                        />
+                    </div>
                     <div className="grid grid-cols-4 items-center gap-4">
                         <Label htmlFor="remark" className="text-right">
                             {t('remark')}
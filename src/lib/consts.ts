@@ -4,5 +4,7 @@ export const GROUP_UPDATE_EVENT_NAME = "group-update";
 export const PROXY_UPDATE_EVENT_NAME = "proxy-update";
 export const CHROME_STARTED_EVENT_NAME = "chrome-started";
 export const CHROME_CLOSED_EVENT_NAME = "chrome-closed";
+export const CHROME_RESTARTED_EVENT_NAME = "chrome-restarted";
 export const CHROME_API_LAUNCH_EVENT_NAME = "chrome-api-launch";
+export const CHROME_API_LAUNCH_FAILED_EVENT_NAME = "chrome-api-launch-failed";
 export const CHROME_API_CLOSE_EVENT_NAME = "chrome-api-close";
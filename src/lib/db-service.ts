@@ -1,5 +1,10 @@
 import Database from "@tauri-apps/plugin-sql";
-import { GroupType, ProfileType, ProxyType } from "./types";
+import {
+  GroupType,
+  ProfileSettingsType,
+  ProfileType,
+  ProxyType,
+} from "./types";
 
 const DB_PATH = "sqlite:shadow.db";
 let db: Database;
@@ -15,6 +20,13 @@ async function getProfiles(groupId: number): Promise<Array<ProfileType>> {
      p.id,
      p.name,
      p.remark,
+     p.browser_path,
+     p.restart_policy,
+     p.restart_max_retries,
+     p.restart_backoff_ms,
+     p.launch_mode,
+     p.extra_args,
+     p.extra_env,
      COALESCE(g.name, 'ungrouped') AS group_name,
      COALESCE(pr.name, 'unproxied') AS proxy_name
      FROM profile_table p
@@ -33,12 +45,25 @@ async function addProfile(
   name: string,
   groupId: number | null,
   proxyId: number | null,
+  settings: ProfileSettingsType,
   remark: string | null
 ) {
   !db && (await connect());
   return await db.execute(
-    `INSERT INTO profile_table (name, group_id, proxy_id, remark) VALUES ($1, $2, $3, $4)`,
-    [name, groupId, proxyId, remark]
+    `INSERT INTO profile_table (name, group_id, proxy_id, browser_path, restart_policy, restart_max_retries, restart_backoff_ms, launch_mode, extra_args, extra_env, remark) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)`,
+    [
+      name,
+      groupId,
+      proxyId,
+      settings.browser_path,
+      settings.restart_policy,
+      settings.restart_max_retries,
+      settings.restart_backoff_ms,
+      settings.launch_mode,
+      settings.extra_args,
+      settings.extra_env,
+      remark,
+    ]
   );
 }
 
@@ -83,12 +108,26 @@ async function updateProfile(
   name: string,
   groupId: number | null,
   proxyId: number | null,
+  settings: ProfileSettingsType,
   remark: string | null
 ) {
   !db && (await connect());
   return await db.execute(
-    `UPDATE profile_table set name = $1, group_id = $2, proxy_id = $3, remark = $4 WHERE id = $5`,
-    [name, groupId, proxyId, remark, id]
+    `UPDATE profile_table set name = $1, group_id = $2, proxy_id = $3, browser_path = $4, restart_policy = $5, restart_max_retries = $6, restart_backoff_ms = $7, launch_mode = $8, extra_args = $9, extra_env = $10, remark = $11 WHERE id = $12`,
+    [
+      name,
+      groupId,
+      proxyId,
+      settings.browser_path,
+      settings.restart_policy,
+      settings.restart_max_retries,
+      settings.restart_backoff_ms,
+      settings.launch_mode,
+      settings.extra_args,
+      settings.extra_env,
+      remark,
+      id,
+    ]
   );
 }
 
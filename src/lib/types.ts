@@ -15,12 +15,60 @@ export type ProfileType = {
   name: string;
   group_name: string | null;
   proxy_name: string | null;
+  browser_path: string | null;
+  restart_policy: RestartPolicyType;
+  restart_max_retries: number;
+  restart_backoff_ms: number;
+  launch_mode: LaunchModeType;
+  extra_args: string | null;
+  extra_env: string | null;
   remark: string | null;
 };
 
+export type RestartPolicyType = "never" | "on_crash" | "always";
+
+export type LaunchModeType = "headed" | "headless" | "virtual_display";
+
+export type ProfileSettingsType = Pick<
+  ProfileType,
+  | "browser_path"
+  | "restart_policy"
+  | "restart_max_retries"
+  | "restart_backoff_ms"
+  | "launch_mode"
+  | "extra_args"
+  | "extra_env"
+>;
+
+export type LaunchExtrasType = {
+  args: string[];
+  env: Record<string, string>;
+};
+
+export type LaunchOverridesType = {
+  port?: number;
+  mode?: LaunchModeType;
+  display_pool?: string;
+  startup_timeout_ms?: number;
+  args?: string[];
+  env?: Record<string, string>;
+};
+
+export type InstalledBrowserType = {
+  kind: string;
+  channel: string;
+  version: string | null;
+  path: string;
+};
+
 export type ProfileStatusType = {
   name: string;
   running: boolean;
   loading: boolean;
   pid?: number;
 };
+
+export type ShadowErrorType = {
+  code: string;
+  message: string;
+};
@@ -1,29 +1,76 @@
 import { clsx, type ClassValue } from "clsx";
 import { twMerge } from "tailwind-merge";
 import { exists, remove, mkdir, BaseDirectory } from "@tauri-apps/plugin-fs";
-import * as path from "@tauri-apps/api/path";
-import { ProfileType } from "./types";
+import { LaunchOverridesType, ProfileType, ShadowErrorType } from "./types";
 import { invoke } from "@tauri-apps/api/core";
-import { load } from "@tauri-apps/plugin-store";
-import { type } from "@tauri-apps/plugin-os";
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
 }
 
+// Credentials may be percent-encoded; IPv6 hosts go in brackets.
 const proxyRegex =
-  /^(http|socks5):\/\/(?:([\w-]+):([\w-]+)@)?([\w.-]+):(\d{1,5})$/;
+  /^(http|socks4a?|socks5|ss):\/\/(?:((?:[\w.~!$&'()*+,;=-]|%[0-9a-fA-F]{2})*)(?::((?:[\w.~!$&'()*+,;=-]|%[0-9a-fA-F]{2})*))?@)?(\[[0-9a-fA-F:.]+\]|[\w.-]+):(\d{1,5})\/?$/;
 
-export const validateProxy = (proxy: string) => {
+// The older host:port:user:pass form, always SOCKS5. A "|" would read as a
+// chain, so it needs the URL form.
+const legacyProxyRegex =
+  /^(\[[0-9a-fA-F:.]+\]|[\w.-]+):(\d{1,5})(?::([^:|]*)(?::([^|]*))?)?$/;
+
+type ProxyParts = {
+  protocol: string;
+  username?: string;
+  password?: string;
+  host: string;
+  port: string;
+  encoded: boolean;
+};
+
+const matchProxy = (proxy: string): ProxyParts | null => {
   const match = proxy.match(proxyRegex);
+  if (match) {
+    const [, protocol, username, password, host, port] = match;
+    return { protocol, username, password, host, port, encoded: true };
+  }
+  const legacy = proxy.match(legacyProxyRegex);
+  if (legacy) {
+    const [, host, port, username, password] = legacy;
+    return { protocol: "socks5", username, password, host, port, encoded: false };
+  }
+  return null;
+};
+
+const decodeCredential = (value: string | undefined, encoded: boolean) => {
+  if (!value) {
+    return null;
+  }
+  if (!encoded) {
+    return value;
+  }
+  try {
+    return decodeURIComponent(value);
+  } catch {
+    return value;
+  }
+};
+
+// Chains list their hops first to last, joined by "|". Only URLs are
+// chained, so a proxy without a scheme is a single legacy one.
+const splitChain = (proxy: string): string[] =>
+  proxy.includes("://")
+    ? proxy.split("|").map((hop) => hop.trim())
+    : [proxy.trim()];
+
+const validateHop = (proxy: string) => {
+  const match = matchProxy(proxy);
   if (!match) {
     return { valid: false, error: "Invalid proxy format" };
   }
-  const protocol = match[1];
-  const username = match[2] || null;
-  const password = match[3] || null;
-  const host = match[4];
-  const port = parseInt(match[5], 10);
+  const protocol = match.protocol;
+  const username = decodeCredential(match.username, match.encoded);
+  const password = decodeCredential(match.password, match.encoded);
+  const host = match.host;
+  const port = parseInt(match.port, 10);
   if (port < 1 || port > 65535) {
     return { valid: false, error: "Invalid port range" };
   }
@@ -37,17 +84,64 @@ export const validateProxy = (proxy: string) => {
   };
 };
 
-export const stripCredentials = (proxy: string): string | "" => {
-  const match = proxy.match(proxyRegex);
+export const validateProxy = (proxy: string) => {
+  const hops = splitChain(proxy).map((hop, _, all) =>
+    all.length > 1 && !hop.includes("://")
+      ? { valid: false, error: "Chained proxies need a URL like socks5://host:port" }
+      : validateHop(hop)
+  );
+  const invalid = hops.findIndex((hop) => !hop.valid);
+  if (invalid >= 0) {
+    const error = hops[invalid].error;
+    return {
+      valid: false,
+      error: hops.length > 1 ? `Hop ${invalid + 1}: ${error}` : error,
+    };
+  }
+  return { ...hops[hops.length - 1], hops };
+};
+
+const stripHop = (proxy: string): string | "" => {
+  const match = matchProxy(proxy);
   if (!match) {
     return "";
   }
-  const protocol = match[1];
-  const host = match[4];
-  const port = match[5];
-  return `${protocol}://${host}:${port}`;
+  return `${match.protocol}://${match.host}:${match.port}`;
+};
+
+export const stripCredentials = (proxy: string): string | "" => {
+  const hops = splitChain(proxy).map(stripHop);
+  return hops.includes("") ? "" : hops.join(" | ");
+};
+
+export const errorMessage = (error: ShadowErrorType | string): string =>
+  typeof error === "string" ? error : error.message;
+
+// Extra args are edited one per line, so values may contain spaces.
+export const parseArgsText = (text: string): string[] =>
+  text
+    .split("\n")
+    .map((line) => line.trim())
+    .filter((line) => line.length > 0);
+
+export const parseEnvText = (text: string): Record<string, string> => {
+  const env: Record<string, string> = {};
+  for (const line of parseArgsText(text)) {
+    const index = line.indexOf("=");
+    if (index < 0) {
+      env[line] = "";
+    } else {
+      env[line.slice(0, index).trim()] = line.slice(index + 1);
+    }
+  }
+  return env;
 };
 
+export const formatEnvText = (env: Record<string, string>): string =>
+  Object.entries(env)
+    .map(([key, value]) => `${key}=${value}`)
+    .join("\n");
+
 export const generateUniqueProfileName = (
   count: number,
   length: number = 6
@@ -65,11 +159,6 @@ export const generateUniqueProfileName = (
   return names;
 };
 
-export const getUserDir = async (name: string) => {
-  const appDir = await path.appDataDir();
-  return `${appDir}/profiles/${name}`;
-};
-
 export const createLocalProfile = async (profileName: string) => {
   const pathExist = await exists(`profiles`, {
     baseDir: BaseDirectory.AppData,
@@ -113,52 +202,15 @@ export function getLastNameFromPath(path: string) {
 }
 
 export async function launchChromeWithProfile(
-  profile: ProfileType,
+  profile: Pick<ProfileType, "id" | "name">,
   onOpenFailed: (name: string, error: string) => void,
-  port?: number
+  overrides?: LaunchOverridesType
 ) {
-  const userDir = await getUserDir(profile.name);
-  console.log("Start to open profile: ", profile, userDir);
-  let validProxy = false;
-  let proxyInfo;
-  if (profile.proxy_name) {
-    proxyInfo = validateProxy(profile.proxy_name);
-    validProxy = proxyInfo.valid;
-  }
-  let socks5 = "";
-  if (validProxy && proxyInfo) {
-    try {
-      const auth =
-        proxyInfo.username && proxyInfo.password
-          ? { user: proxyInfo.username, pass: proxyInfo.password }
-          : undefined;
-      socks5 = await invoke("start_proxy", {
-        proxy: { ip: proxyInfo.host, port: proxyInfo.port, auth },
-      });
-      console.log({ socks5 });
-    } catch (error: any) {
-      onOpenFailed(profile.name, error.toString());
-      console.log(`Start proxy error: ${error}`);
-      return;
-    }
-  }
-  let win_chrome_path;
-  const osType = await type();
-  const isWin = osType == "windows";
-  if (isWin) {
-    const store = await load("settings.json");
-    win_chrome_path = await store.get("chrome_path");
-  }
-  invoke("launch_chrome", {
-    id: profile.id,
-    userDir,
-    port,
-    proxy: socks5 ? `socks5://${socks5}` : undefined,
-    win_chrome_path,
-  })
+  console.log("Start to open profile: ", profile);
+  invoke("launch_profile", { id: profile.id, overrides })
     .then(console.log)
     .catch((error: any) => {
-      onOpenFailed(profile.name, error);
-      console.log(`Launch chrome error: ${error}`);
+      onOpenFailed(profile.name, errorMessage(error));
+      console.log("Launch chrome error:", error);
     });
 }
@@ -3,9 +3,9 @@ import { DataTable } from "./data-table"
 import { useEffect, useRef, useState } from "react"
 import { getProfiles } from "@/lib/db-service"
 import { listen } from '@tauri-apps/api/event'
-import { CHROME_API_CLOSE_EVENT_NAME, CHROME_API_LAUNCH_EVENT_NAME, CHROME_CLOSED_EVENT_NAME, CHROME_STARTED_EVENT_NAME, PROFILE_REFRESH_EVENT_NAME } from "@/lib/consts"
+import { CHROME_API_CLOSE_EVENT_NAME, CHROME_API_LAUNCH_EVENT_NAME, CHROME_API_LAUNCH_FAILED_EVENT_NAME, CHROME_CLOSED_EVENT_NAME, CHROME_RESTARTED_EVENT_NAME, CHROME_STARTED_EVENT_NAME, PROFILE_REFRESH_EVENT_NAME } from "@/lib/consts"
 import { invoke } from "@tauri-apps/api/core"
-import { getLastNameFromPath, launchChromeWithProfile } from "@/lib/utils"
+import { errorMessage, getLastNameFromPath } from "@/lib/utils"
 import { getColumns } from "./columns"
 import { useTranslation } from "react-i18next"
 import { toast } from "sonner"
@@ -17,7 +17,9 @@ export function ProfilePage() {
     const unlistenRef = useRef<(() => void) | null>(null)
     const unlistenChromeCloseRef = useRef<(() => void) | null>(null)
     const unlistenChromeStartRef = useRef<(() => void) | null>(null)
+    const unlistenChromeRestartRef = useRef<(() => void) | null>(null)
     const unlistenChromeApiLaunchRef = useRef<(() => void) | null>(null)
+    const unlistenChromeApiLaunchFailedRef = useRef<(() => void) | null>(null)
     const unlistenChromeApiCloseRef = useRef<(() => void) | null>(null)
     const curGroupIdRef = useRef<number>(0)
     const tableRef = useRef<any>(null)
@@ -44,29 +46,22 @@ export function ProfilePage() {
 
     useEffect(() => {
         const setupListener = async () => {
-            unlistenChromeApiLaunchRef.current = await listen(CHROME_API_LAUNCH_EVENT_NAME, async ({ payload }) => {
+            unlistenChromeApiLaunchRef.current = await listen(CHROME_API_LAUNCH_EVENT_NAME, ({ payload }) => {
                 console.log(`CHROME API LAUNCH EVENT:`, payload)
-                setRunningData(prev => [...prev, { name, running: false, loading: true }])
-                const id = (payload as any).id
                 const name = (payload as any).name
-                const port = (payload as any).port
-                const proxy_name = (payload as any).proxy_name
-                const profile: ProfileType = {
-                    id, name, proxy_name,
-                    group_name: null,
-                    remark: null
-                }
-                await launchChromeWithProfile(profile, (name, error) => {
-                    setRunningData(prev => prev.filter(item => item.name != name))
-                    toast(t('open_chrome_failed', { name, error }))
-                }, port)
+                setRunningData(prev => [...prev.filter(item => item.name != name), { name, running: false, loading: true }])
+            });
+            unlistenChromeApiLaunchFailedRef.current = await listen(CHROME_API_LAUNCH_FAILED_EVENT_NAME, ({ payload }) => {
+                console.log(`CHROME API LAUNCH FAILED EVENT:`, payload)
+                const name = (payload as any).name
+                const error = errorMessage((payload as any).error)
+                setRunningData(prev => prev.filter(item => item.name != name))
+                toast(t('open_chrome_failed', { name, error }))
             });
             unlistenChromeApiCloseRef.current = await listen(CHROME_API_CLOSE_EVENT_NAME, async ({ payload }) => {
                 console.log(`CHROME API CLOSE EVENT:`, payload)
                 const name = (payload as any).name
-                const pid = Number((payload as any).pid)
                 setRunningData(prev => prev.map(p => p.name == name ? { name, pid: p.pid, running: p.running, loading: true } : p))
-                invoke('close_chrome', { pid }).then(console.log).catch(console.error)
             })
             unlistenChromeStartRef.current = await listen(CHROME_STARTED_EVENT_NAME, ({ payload }) => {
                 console.log(`CHROME START EVENT:`, payload)
@@ -75,32 +70,20 @@ export function ProfilePage() {
                 const ws = (payload as any).ws
                 const name = getLastNameFromPath(path)!
                 console.log({ pid }, { name }, { ws })
-                setRunningData(prev => prev.map(p => p.name == name ? { name, pid, running: true, loading: false } : p))
+                // Instances started through the API or restarted may not be listed yet.
+                setRunningData(prev => [...prev.filter(p => p.name != name), { name, pid, running: true, loading: false }])
             })
             unlistenChromeCloseRef.current = await listen(CHROME_CLOSED_EVENT_NAME, async ({ payload }) => {
                 console.log(`CHROME CLOSE EVENT:`, payload)
                 const pid = Number((payload as any).pid)
-                let proxy = (payload as any).proxy
-                if (proxy) {
-                    proxy = (proxy as string).slice(9)
-                    const localProxies: any[] = await invoke('list_proxy')
-                    console.log({ localProxies })
-                    const findProxy: string = localProxies.find(lp => lp.addr == proxy)?.addr
-                    if (findProxy) {
-                        const ip = findProxy.split(':')[0]
-                        const port = Number(findProxy.split(':')[1])
-                        console.log(`Start stop local proxy: ${ip}:${port}`)
-                        try {
-                            await invoke('stop_proxy', { proxy: { ip, port } })
-                        } catch (error) {
-                            console.log(`Stop proxy error: ${error}`)
-                        }
-                    } else {
-                        console.log("Local proxy find failed...")
-                    }
-                }
                 setRunningData(prev => prev.filter(item => item.pid != pid))
             })
+            unlistenChromeRestartRef.current = await listen(CHROME_RESTARTED_EVENT_NAME, ({ payload }) => {
+                console.log(`CHROME RESTART EVENT:`, payload)
+                const name = getLastNameFromPath((payload as any).user_dir)!
+                const attempt = (payload as any).attempt
+                toast(t('chrome_restarted', { name, attempt }))
+            })
         }
         setupListener();
         return () => {
@@ -110,9 +93,15 @@ export function ProfilePage() {
             if (unlistenChromeCloseRef.current) {
                 unlistenChromeCloseRef.current()
             }
+            if (unlistenChromeRestartRef.current) {
+                unlistenChromeRestartRef.current()
+            }
             if (unlistenChromeApiLaunchRef.current) {
                 unlistenChromeApiLaunchRef.current()
             }
+            if (unlistenChromeApiLaunchFailedRef.current) {
+                unlistenChromeApiLaunchFailedRef.current()
+            }
             if (unlistenChromeApiCloseRef.current) {
                 unlistenChromeApiCloseRef.current()
             }
@@ -135,7 +135,10 @@ export const NewProxyBtn = ({ t, onProxyAdded, open, setOpen, editProxy, setEdit
                                         <HelpCircleIcon size={15} />
                                     </TooltipTrigger>
                                     <TooltipContent>
-                                        <p>http(socks5)://username:password@host:port</p>
+                                        <p>http(socks4, socks4a, socks5)://username:password@host:port</p>
+                                        <p>ss://cipher:password@host:port</p>
+                                        <p>proxy1|proxy2|...</p>
+                                        <p>host:port:username:password, [IPv6]:port</p>
                                     </TooltipContent>
                                 </Tooltip>
                             </TooltipProvider>